@@ -1,12 +1,51 @@
 # coding=utf8
 import datetime
+import html
 import json
 
+import rss_pipe
+
 statistics = [
     "select id, item_id, reply_id, data, datetime(create_time, '+8 hours') from blob_storage where item_id > 0",
     "select url, counter, datetime(update_time, '+8 hours') from item where counter > 0 order by update_time desc"
 ]
 
+schedule = {
+    "https://example.com/feed.xml": 600,
+}
+
+transforms = {
+    r"^https://example\.com/api/posts": "example_posts",
+}
+
+
+def process_item(feed_url, item):
+    if feed_url.startswith("https://example.com/"):
+        if "sponsored" in item["title"].lower():
+            return False
+        item["tags"] = ["example"]
+        # drop reposts of links already saved with another guid
+        if rss_pipe.query("select 1 from item where url = ? and guid != ?", [item["link"], item["id"]]):
+            rss_pipe.log(f"dropping repost {item['link']}")
+            return False
+
+
+def transform_example_posts(body, headers):
+    posts = json.loads(body)
+    entries = "\n".join(f"""<entry>
+        <title>{html.escape(v["title"])}</title>
+        <id>{v["id"]}</id>
+        <updated>{v["date"]}</updated>
+        <link href="{html.escape(v["url"])}" rel="alternate"/>
+    </entry>""" for v in posts)
+    return f"""<?xml version="1.0" encoding="utf-8"?>
+    <feed xmlns="http://www.w3.org/2005/Atom">
+        <title>Example Posts</title>
+        <id>https://example.com/api/posts</id>
+        <updated>{headers.get("last-modified", "")}</updated>
+    {entries}
+</feed>"""
+
 
 def from_grafana_alert_item(v):
     return f"""<entry>
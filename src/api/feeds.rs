@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::Response;
+use rusqlite::Transaction;
+use serde::{Deserialize, Serialize};
+
+use crate::api::json;
+use crate::common;
+use crate::storage::{feeds, groups};
+
+#[derive(Serialize, Debug)]
+pub struct FeedUrlApi {
+    id: u64,
+    url: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FeedApi {
+    id: u64,
+    title: String,
+    site_url: String,
+    last_updated: u64,
+    urls: Vec<FeedUrlApi>,
+    groups: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct FeedRequest {
+    title: Option<String>,
+    url: Option<String>,
+    groups: Option<Vec<String>>,
+}
+
+pub fn get_all_feeds(tx: &Transaction) -> Result<Vec<FeedApi>, rusqlite::Error> {
+    let mut urls: HashMap<u64, Vec<FeedUrlApi>> = HashMap::new();
+    for feed_url in feeds::get_all_feed_urls(tx)? {
+        urls.entry(feed_url.feed_id).or_default().push(FeedUrlApi {
+            id: feed_url.id,
+            url: feed_url.url,
+        });
+    }
+    let mut feed_groups = groups::get_feed_group_titles(tx)?;
+    let all_feeds = feeds::get_all_feeds(tx)?
+        .into_iter()
+        .map(|(feed, _)| FeedApi {
+            id: feed.id,
+            urls: urls.remove(&feed.id).unwrap_or_default(),
+            groups: feed_groups.remove(&feed.id).unwrap_or_default(),
+            title: feed.title,
+            site_url: feed.site_url,
+            last_updated: feed.last_updated,
+        })
+        .collect();
+    Ok(all_feeds)
+}
+
+pub fn get_feed(tx: &Transaction, id: &str) -> Result<Response<Full<Bytes>>, common::PipeError> {
+    match get_all_feeds(tx)?.into_iter().find(|feed| feed.id.to_string() == id) {
+        Some(feed) => json(&feed),
+        None => common::not_found(),
+    }
+}
+
+fn apply_feed_request(tx: &Transaction, feed_id: u64, request: &FeedRequest) -> Result<(), rusqlite::Error> {
+    // the url is the only field that can conflict, apply it first
+    if let Some(url) = &request.url {
+        feeds::set_feed_url(tx, feed_id, url)?;
+    }
+    if let Some(title) = &request.title {
+        feeds::rename_feed(tx, feed_id, title)?;
+    }
+    if let Some(titles) = &request.groups {
+        groups::set_feed_groups(tx, feed_id, titles)?;
+    }
+    Ok(())
+}
+
+pub fn create_feed(tx: &Transaction, body: &str) -> Result<Response<Full<Bytes>>, common::PipeError> {
+    let request: FeedRequest = match serde_json::from_str(body) {
+        Ok(v) => v,
+        Err(_) => return common::bad_request(),
+    };
+    let url = match &request.url {
+        Some(v) if v.starts_with("http://") || v.starts_with("https://") => v,
+        _ => return common::bad_request(),
+    };
+    if feeds::get_feed_id_by_url(tx, url)?.is_some() {
+        return common::conflict();
+    }
+    let (feed_id, _, _) = feeds::upsert_feed(tx, url, Some(request.title.as_deref().unwrap_or(url)))?;
+    println!("creating new feed {url} [{feed_id}] from api");
+    groups::set_feed_groups(tx, feed_id, request.groups.as_deref().unwrap_or_default())?;
+    get_feed(tx, &feed_id.to_string())
+}
+
+pub fn update_feed(tx: &Transaction, id: &str, body: &str) -> Result<Response<Full<Bytes>>, common::PipeError> {
+    let request: FeedRequest = match serde_json::from_str(body) {
+        Ok(v) => v,
+        Err(_) => return common::bad_request(),
+    };
+    let feed_id = match id.parse() {
+        Ok(v) if feeds::feed_exists(tx, v)? => v,
+        _ => return common::not_found(),
+    };
+    apply_feed_request(tx, feed_id, &request)?;
+    get_feed(tx, id)
+}
+
+pub fn delete_feed(tx: &Transaction, id: &str) -> Result<Response<Full<Bytes>>, common::PipeError> {
+    let feed_id = match id.parse() {
+        Ok(v) => v,
+        Err(_) => return common::not_found(),
+    };
+    match feeds::delete_feed(tx, feed_id)? {
+        0 => common::not_found(),
+        _ => {
+            println!("deleted feed [{id}] from api");
+            common::json_response("{}")
+        }
+    }
+}
+
+pub fn add_feed_url(tx: &Transaction, id: &str, body: &str) -> Result<Response<Full<Bytes>>, common::PipeError> {
+    let request: FeedRequest = match serde_json::from_str(body) {
+        Ok(v) => v,
+        Err(_) => return common::bad_request(),
+    };
+    match (id.parse::<u64>(), &request.url) {
+        (Ok(feed_id), _) if !feeds::feed_exists(tx, feed_id)? => common::not_found(),
+        (Ok(feed_id), Some(url)) => {
+            if !feeds::add_feed_url(tx, feed_id, url)? {
+                return common::conflict();
+            }
+            get_feed(tx, id)
+        }
+        _ => common::bad_request(),
+    }
+}
+
+pub fn delete_feed_url(tx: &Transaction, id: &str, url_id: &str) -> Result<Response<Full<Bytes>>, common::PipeError> {
+    match (id.parse(), url_id.parse()) {
+        (Ok(feed_id), Ok(url_id)) => match feeds::delete_feed_url(tx, feed_id, url_id)? {
+            0 => common::bad_request(), // unknown url or the last url of the feed
+            _ => get_feed(tx, id),
+        },
+        _ => common::not_found(),
+    }
+}
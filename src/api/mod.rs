@@ -0,0 +1,98 @@
+use bytes::Bytes;
+use http::{HeaderMap, Method, StatusCode};
+use http_body_util::Full;
+use hyper::{Request, Response, body::Incoming};
+use serde::Serialize;
+use url::form_urlencoded;
+
+use crate::{common, pipe, storage};
+
+mod feeds;
+mod opml;
+mod retention;
+mod revisions;
+mod rules;
+mod search;
+
+fn is_authorized(auth: Option<&str>, headers: &HeaderMap, query: &str) -> bool {
+    let Some(auth) = auth else {
+        return false;
+    };
+    let bearer = headers
+        .get(http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let api_key = form_urlencoded::parse(query.as_bytes()).find(|(k, _)| k == "api_key");
+    bearer == Some(auth) || api_key.is_some_and(|(_, v)| v == auth)
+}
+
+fn json<T: Serialize>(v: &T) -> Result<Response<Full<Bytes>>, common::PipeError> {
+    common::json_response(&serde_json::to_string(v)?)
+}
+
+pub async fn api(
+    db: &str,
+    auth: Option<&str>,
+    prefix: &str,
+    path: &str,
+    req: Request<Incoming>,
+) -> Result<Response<Full<Bytes>>, common::PipeError> {
+    let query = req.uri().query().unwrap_or("").to_owned();
+    if !is_authorized(auth, req.headers(), &query) {
+        return common::unauthorized();
+    }
+    // subscriptions are exported with urls rewritten to go through this service if requested
+    let rewrite_prefix = form_urlencoded::parse(query.as_bytes())
+        .any(|(k, v)| k == "rewrite" && v == "1")
+        .then_some(prefix);
+    let method = req.method().to_owned();
+    let body = common::parse_request_body(req).await;
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    storage::transaction_for(db, method != Method::GET, |tx| match (&method, segments.as_slice()) {
+        (&Method::GET, ["feeds"]) => json(&feeds::get_all_feeds(tx)?),
+        (&Method::POST, ["feeds"]) => feeds::create_feed(tx, &body),
+        (&Method::GET, ["feeds", id]) => feeds::get_feed(tx, id),
+        (&Method::PUT | &Method::PATCH, ["feeds", id]) => feeds::update_feed(tx, id, &body),
+        (&Method::DELETE, ["feeds", id]) => feeds::delete_feed(tx, id),
+        (&Method::POST, ["feeds", id, "urls"]) => feeds::add_feed_url(tx, id, &body),
+        (&Method::DELETE, ["feeds", id, "urls", url_id]) => feeds::delete_feed_url(tx, id, url_id),
+        (&Method::PUT, ["feeds", id, "rule"]) => rules::set_push_rule(tx, "feed", id, &body),
+        (&Method::DELETE, ["feeds", id, "rule"]) => rules::delete_push_rule(tx, "feed", id),
+        (&Method::PUT, ["feeds", id, "retention"]) => retention::set_retention_rule(tx, id, &body),
+        (&Method::DELETE, ["feeds", id, "retention"]) => retention::delete_retention_rule(tx, id),
+        (&Method::GET, ["groups"]) => json(&rules::get_all_groups(tx)?),
+        (&Method::PUT, ["groups", id, "rule"]) => rules::set_push_rule(tx, "group", id, &body),
+        (&Method::DELETE, ["groups", id, "rule"]) => rules::delete_push_rule(tx, "group", id),
+        (&Method::GET, ["rules"]) => json(&rules::get_all_push_rules(tx)?),
+        (&Method::GET, ["retention"]) => json(&retention::get_all_retention_rules(tx)?),
+        (&Method::GET, ["opml"]) => opml::export(tx, rewrite_prefix),
+        (&Method::POST, ["opml"]) => opml::import(tx, prefix, &body),
+        (&Method::GET, ["items", id, "revisions"]) => revisions::get_revisions(tx, id),
+        (&Method::GET, ["items", id, "diff"]) => revisions::diff(tx, id, &query),
+        (&Method::GET, ["search"]) => search::search(tx, &query),
+        (&Method::GET, ["search.atom"]) => search::search_feed(tx, &query),
+        _ => common::not_found(),
+    })
+}
+
+pub async fn reload(
+    auth: Option<&str>,
+    pipe: &pipe::Pipe,
+    req: Request<Incoming>,
+) -> Result<Response<Full<Bytes>>, common::PipeError> {
+    let query = req.uri().query().unwrap_or("");
+    if !is_authorized(auth, req.headers(), query) {
+        return common::unauthorized();
+    }
+    if req.method() != Method::POST {
+        return common::not_found();
+    }
+    match pipe.reload() {
+        Ok(_) => common::json_response("{}"),
+        Err(traceback) => Response::builder()
+            .status(StatusCode::UNPROCESSABLE_ENTITY)
+            .header(http::header::CONTENT_TYPE, "text/plain; charset=utf-8")
+            .body(Full::from(traceback))
+            .map_err(|e| e.into()),
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use http::StatusCode;
+use http_body_util::Full;
+use hyper::Response;
+use quick_xml::{
+    Decoder, Reader, Writer,
+    events::{BytesDecl, BytesStart, BytesText, Event},
+};
+use rusqlite::Transaction;
+use serde::Serialize;
+use url::Url;
+
+use crate::api::json;
+use crate::common;
+use crate::storage::{feeds, groups};
+
+#[derive(Debug, Default)]
+struct Outline {
+    title: String,
+    xml_url: Option<String>,
+    html_url: Option<String>,
+    categories: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct ImportResult {
+    created: u64,
+    existing: u64,
+    skipped: u64,
+}
+
+fn parse_outline(e: &BytesStart, decoder: Decoder) -> Outline {
+    let mut outline = Outline::default();
+    let mut text = String::new();
+    for attribute in e.attributes().flatten() {
+        let value = match attribute.decode_and_unescape_value(decoder) {
+            Ok(v) => v.trim().to_owned(),
+            Err(_) => continue,
+        };
+        match attribute.key.as_ref().to_ascii_lowercase().as_slice() {
+            b"title" => outline.title = value,
+            b"text" => text = value,
+            b"xmlurl" => outline.xml_url = Some(value).filter(|v| !v.is_empty()),
+            b"htmlurl" => outline.html_url = Some(value).filter(|v| !v.is_empty()),
+            // comma separated, slash delimited category paths; only the last part is used as group
+            b"category" => {
+                outline.categories = value
+                    .split(',')
+                    .filter_map(|v| v.trim_matches('/').rsplit('/').next())
+                    .filter(|v| !v.is_empty())
+                    .map(|v| v.to_owned())
+                    .collect()
+            }
+            _ => {}
+        }
+    }
+    if outline.title.is_empty() {
+        outline.title = text;
+    }
+    outline
+}
+
+fn parse_opml(body: &str) -> Option<Vec<(Outline, Option<String>)>> {
+    let mut reader = Reader::from_str(body);
+    reader.config_mut().trim_text(true);
+    // folders are outlines without `xmlUrl`, the innermost folder becomes the group of a feed
+    let mut folders: Vec<Option<String>> = vec![];
+    let mut outlines = vec![];
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) if e.name().as_ref() == b"outline" => {
+                let outline = parse_outline(&e, reader.decoder());
+                if outline.xml_url.is_some() {
+                    folders.push(folders.last().cloned().flatten());
+                    outlines.push((outline, folders.last().cloned().flatten()));
+                } else {
+                    folders.push(Some(outline.title).filter(|v| !v.is_empty()));
+                }
+            }
+            Ok(Event::Empty(e)) if e.name().as_ref() == b"outline" => {
+                let outline = parse_outline(&e, reader.decoder());
+                if outline.xml_url.is_some() {
+                    outlines.push((outline, folders.last().cloned().flatten()));
+                }
+            }
+            Ok(Event::End(e)) if e.name().as_ref() == b"outline" => {
+                folders.pop();
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => {
+                println!("!! error parsing opml: {e}");
+                return None;
+            }
+        }
+    }
+    Some(outlines)
+}
+
+fn restore_url(prefix: &str, url: &str) -> Option<String> {
+    // undo the `/http/` and `/https/` rewriting used by bots subscribing through this service
+    let parsed = Url::parse(url).ok()?;
+    let path = match Url::parse(prefix) {
+        Ok(v)
+            if v.scheme() == parsed.scheme()
+                && v.host_str() == parsed.host_str()
+                && v.port_or_known_default() == parsed.port_or_known_default() =>
+        {
+            parsed.path().strip_prefix(v.path()).map(|v| v.trim_start_matches('/'))
+        }
+        _ => None,
+    };
+    let full_url = match path.and_then(|v| v.strip_prefix("https/")) {
+        Some(v) => format!("https://{v}"),
+        None => match path.and_then(|v| v.strip_prefix("http/")) {
+            Some(v) => format!("http://{v}"),
+            None => return Some(url.to_owned()).filter(|_| matches!(parsed.scheme(), "http" | "https")),
+        },
+    };
+    match parsed.query() {
+        Some(query) => Some(format!("{full_url}?{query}")),
+        None => Some(full_url),
+    }
+}
+
+fn rewrite_url(prefix: &str, url: &str) -> String {
+    match url.split_once("://") {
+        Some((scheme, rest)) => format!("{prefix}{scheme}/{rest}"),
+        None => url.to_owned(),
+    }
+}
+
+pub fn import(tx: &Transaction, prefix: &str, body: &str) -> Result<Response<Full<Bytes>>, common::PipeError> {
+    let outlines = match parse_opml(body) {
+        Some(v) => v,
+        None => return common::bad_request(),
+    };
+    let mut result = ImportResult::default();
+    for (outline, folder) in outlines {
+        let url = match outline.xml_url.as_deref().and_then(|v| restore_url(prefix, v)) {
+            Some(v) => v,
+            None => {
+                result.skipped += 1;
+                continue;
+            }
+        };
+        let feed_id = match feeds::get_feed_id_by_url(tx, &url)? {
+            Some(v) => {
+                result.existing += 1;
+                v
+            }
+            None => {
+                let title = if outline.title.is_empty() { &url } else { &outline.title };
+                let (feed_id, _, _) = feeds::upsert_feed(tx, &url, Some(title))?;
+                if let Some(html_url) = &outline.html_url {
+                    feeds::set_site_url(tx, feed_id, html_url)?;
+                }
+                println!("creating new feed {title} [{feed_id}] {url} from opml");
+                result.created += 1;
+                feed_id
+            }
+        };
+        // groups are only added, existing memberships are kept
+        for title in folder.iter().chain(outline.categories.iter()) {
+            groups::add_feed_group(tx, feed_id, title)?;
+        }
+    }
+    json(&result)
+}
+
+fn write_feed(
+    writer: &mut Writer<Vec<u8>>,
+    feed: &feeds::Feed,
+    url: &str,
+    prefix: Option<&str>,
+) -> std::io::Result<()> {
+    let xml_url = match prefix {
+        Some(v) => rewrite_url(v, url),
+        None => url.to_owned(),
+    };
+    writer
+        .create_element("outline")
+        .with_attributes([
+            ("type", "rss"),
+            ("text", feed.title.as_str()),
+            ("title", feed.title.as_str()),
+            ("xmlUrl", xml_url.as_str()),
+            ("htmlUrl", feed.site_url.as_str()),
+        ])
+        .write_empty()?;
+    Ok(())
+}
+
+pub fn export(tx: &Transaction, prefix: Option<&str>) -> Result<Response<Full<Bytes>>, common::PipeError> {
+    let all_feeds = feeds::get_all_feeds(tx)?;
+    let feed_groups = groups::get_feed_group_titles(tx)?;
+    let mut grouped: HashMap<&str, Vec<&(feeds::Feed, feeds::FeedUrl)>> = HashMap::new();
+    let mut ungrouped = vec![];
+    for entry in &all_feeds {
+        match feed_groups.get(&entry.0.id) {
+            Some(titles) => titles
+                .iter()
+                .for_each(|title| grouped.entry(title).or_default().push(entry)),
+            None => ungrouped.push(entry),
+        }
+    }
+    let mut group_titles: Vec<&str> = grouped.keys().copied().collect();
+    group_titles.sort();
+
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    writer
+        .create_element("opml")
+        .with_attribute(("version", "2.0"))
+        .write_inner_content(|writer| {
+            writer.create_element("head").write_inner_content(|writer| {
+                writer
+                    .create_element("title")
+                    .write_text_content(BytesText::new("rss_pipe subscriptions"))?;
+                Ok(())
+            })?;
+            writer.create_element("body").write_inner_content(|writer| {
+                for title in &group_titles {
+                    writer
+                        .create_element("outline")
+                        .with_attributes([("text", *title), ("title", *title)])
+                        .write_inner_content(|writer| {
+                            for (feed, feed_url) in grouped.get(title).map(|v| v.as_slice()).unwrap_or_default() {
+                                write_feed(writer, feed, &feed_url.url, prefix)?;
+                            }
+                            Ok(())
+                        })?;
+                }
+                for (feed, feed_url) in &ungrouped {
+                    write_feed(writer, feed, &feed_url.url, prefix)?;
+                }
+                Ok(())
+            })?;
+            Ok(())
+        })?;
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "text/x-opml; charset=utf-8")
+        .header(
+            http::header::CONTENT_DISPOSITION,
+            "attachment; filename=\"rss_pipe.opml\"",
+        )
+        .body(Full::from(writer.into_inner()))
+        .map_err(|e| e.into())
+}
@@ -0,0 +1,70 @@
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::Response;
+use rusqlite::Transaction;
+use serde::{Deserialize, Serialize};
+
+use crate::api::json;
+use crate::common;
+use crate::storage::{feeds, retention};
+
+fn default_keep() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RetentionRuleApi {
+    #[serde(skip_deserializing)]
+    feed_id: u64,
+    max_age: Option<u64>,
+    max_items: Option<u64>,
+    #[serde(default = "default_keep")]
+    keep_saved: bool,
+    #[serde(default = "default_keep")]
+    keep_commented: bool,
+}
+
+pub fn get_all_retention_rules(tx: &Transaction) -> Result<Vec<RetentionRuleApi>, rusqlite::Error> {
+    let retention_rules = retention::get_all_retention_rules(tx)?
+        .into_iter()
+        .map(|rule| RetentionRuleApi {
+            feed_id: rule.feed_id,
+            max_age: rule.max_age,
+            max_items: rule.max_items,
+            keep_saved: rule.keep_saved,
+            keep_commented: rule.keep_commented,
+        })
+        .collect();
+    Ok(retention_rules)
+}
+
+pub fn set_retention_rule(tx: &Transaction, id: &str, body: &str) -> Result<Response<Full<Bytes>>, common::PipeError> {
+    let feed_id = match id.parse() {
+        Ok(v) if feeds::feed_exists(tx, v)? => v,
+        _ => return common::not_found(),
+    };
+    let request: RetentionRuleApi = match serde_json::from_str(body) {
+        Ok(v) => v,
+        Err(_) => return common::bad_request(),
+    };
+    let rule = retention::RetentionRule {
+        feed_id,
+        max_age: request.max_age,
+        max_items: request.max_items,
+        keep_saved: request.keep_saved,
+        keep_commented: request.keep_commented,
+    };
+    retention::set_retention_rule(tx, &rule)?;
+    json(&get_all_retention_rules(tx)?.into_iter().find(|v| v.feed_id == feed_id))
+}
+
+pub fn delete_retention_rule(tx: &Transaction, id: &str) -> Result<Response<Full<Bytes>>, common::PipeError> {
+    let feed_id = match id.parse() {
+        Ok(v) => v,
+        Err(_) => return common::not_found(),
+    };
+    match retention::delete_retention_rule(tx, feed_id)? {
+        0 => common::not_found(),
+        _ => common::json_response("{}"),
+    }
+}
@@ -0,0 +1,81 @@
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::Response;
+use nanohtml2text::html2text;
+use rusqlite::Transaction;
+use serde::Serialize;
+use similar::TextDiff;
+use url::form_urlencoded;
+
+use crate::api::json;
+use crate::common;
+use crate::storage::{items, revisions};
+
+#[derive(Serialize, Debug)]
+pub struct ItemRevisionApi {
+    id: u64,
+    item_id: u64,
+    title: String,
+    replaced: u64,
+}
+
+fn get_item(tx: &Transaction, id: &str) -> Result<Option<items::Item>, rusqlite::Error> {
+    if id.parse::<u64>().is_err() {
+        return Ok(None);
+    }
+    Ok(items::get_items(tx, "with_ids", id, 1)?.into_iter().next())
+}
+
+fn to_text(title: &str, html: &str) -> String {
+    // compared as text, markup changes alone are not worth a diff
+    format!("{}\n\n{}\n", title, html2text(html).trim())
+}
+
+pub fn get_revisions(tx: &Transaction, id: &str) -> Result<Response<Full<Bytes>>, common::PipeError> {
+    let item = match get_item(tx, id)? {
+        Some(v) => v,
+        None => return common::not_found(),
+    };
+    let revisions: Vec<ItemRevisionApi> = revisions::get_revisions(tx, item.id)?
+        .into_iter()
+        .map(|revision| ItemRevisionApi {
+            id: revision.id,
+            item_id: revision.item_id,
+            title: revision.title,
+            replaced: revision.create_time,
+        })
+        .collect();
+    json(&revisions)
+}
+
+/// Unified diff of a revision (the latest one by default) against the version replacing it
+pub fn diff(tx: &Transaction, id: &str, query: &str) -> Result<Response<Full<Bytes>>, common::PipeError> {
+    let item = match get_item(tx, id)? {
+        Some(v) => v,
+        None => return common::not_found(),
+    };
+    let revision_id = form_urlencoded::parse(query.as_bytes())
+        .find(|(k, _)| k == "revision")
+        .and_then(|(_, v)| v.parse::<u64>().ok());
+    // newest first
+    let revisions = revisions::get_revisions(tx, item.id)?;
+    let index = match revision_id {
+        Some(v) => revisions.iter().position(|revision| revision.id == v),
+        None => (!revisions.is_empty()).then_some(0),
+    };
+    let index = match index {
+        Some(v) => v,
+        None => return common::not_found(),
+    };
+    let old = &revisions[index];
+    let (new_label, new_text) = match index.checked_sub(1).map(|v| &revisions[v]) {
+        Some(newer) => (format!("revision {}", newer.id), to_text(&newer.title, &newer.content)),
+        None => ("current".to_owned(), to_text(&item.title, &item.html)),
+    };
+    let old_text = to_text(&old.title, &old.content);
+    let diff = TextDiff::from_lines(&old_text, &new_text)
+        .unified_diff()
+        .header(&format!("revision {}", old.id), &new_label)
+        .to_string();
+    common::text_response(&diff)
+}
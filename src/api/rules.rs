@@ -0,0 +1,120 @@
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::Response;
+use rusqlite::Transaction;
+use serde::{Deserialize, Serialize};
+
+use crate::api::json;
+use crate::common;
+use crate::push::rules;
+use crate::storage::{feeds, groups, push_rules};
+
+#[derive(Serialize, Debug)]
+pub struct GroupApi {
+    id: u64,
+    title: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PushRuleApi {
+    #[serde(skip_deserializing)]
+    target: String,
+    #[serde(skip_deserializing)]
+    target_id: u64,
+    mode: String,
+    #[serde(default)]
+    keywords: Vec<String>,
+    #[serde(default)]
+    quiet_start: String,
+    #[serde(default)]
+    quiet_end: String,
+}
+
+pub fn get_all_groups(tx: &Transaction) -> Result<Vec<GroupApi>, rusqlite::Error> {
+    let all_groups = groups::get_all_groups(tx)?
+        .into_iter()
+        .map(|group| GroupApi {
+            id: group.id,
+            title: group.title,
+        })
+        .collect();
+    Ok(all_groups)
+}
+
+pub fn get_all_push_rules(tx: &Transaction) -> Result<Vec<PushRuleApi>, rusqlite::Error> {
+    let push_rules = push_rules::get_all_push_rules(tx)?
+        .into_iter()
+        .map(|rule| PushRuleApi {
+            target: rule.target,
+            target_id: rule.target_id,
+            mode: rule.mode,
+            keywords: rule.keywords,
+            quiet_start: rule.quiet_start,
+            quiet_end: rule.quiet_end,
+        })
+        .collect();
+    Ok(push_rules)
+}
+
+fn get_target_id(tx: &Transaction, target: &str, id: &str) -> Result<Option<u64>, rusqlite::Error> {
+    let target_id = match id.parse() {
+        Ok(v) => v,
+        Err(_) => return Ok(None),
+    };
+    let exists = match target {
+        "feed" => feeds::feed_exists(tx, target_id)?,
+        _ => groups::get_all_groups(tx)?.iter().any(|group| group.id == target_id),
+    };
+    Ok(exists.then_some(target_id))
+}
+
+pub fn set_push_rule(
+    tx: &Transaction,
+    target: &str,
+    id: &str,
+    body: &str,
+) -> Result<Response<Full<Bytes>>, common::PipeError> {
+    let target_id = match get_target_id(tx, target, id)? {
+        Some(v) => v,
+        None => return common::not_found(),
+    };
+    let mut request: PushRuleApi = match serde_json::from_str(body) {
+        Ok(v) => v,
+        Err(_) => return common::bad_request(),
+    };
+    request.keywords.retain(|v| !v.trim().is_empty());
+    let quiet_hours = (request.quiet_start.as_str(), request.quiet_end.as_str());
+    let quiet_hours_valid = quiet_hours == ("", "")
+        || (rules::parse_time(quiet_hours.0).is_some() && rules::parse_time(quiet_hours.1).is_some());
+    if !rules::MODES.contains(&request.mode.as_str())
+        || (request.mode == "keyword" && request.keywords.is_empty())
+        || !quiet_hours_valid
+    {
+        return common::bad_request();
+    }
+    let rule = push_rules::PushRule {
+        target: target.to_owned(),
+        target_id,
+        mode: request.mode,
+        keywords: request.keywords,
+        quiet_start: request.quiet_start,
+        quiet_end: request.quiet_end,
+    };
+    push_rules::set_push_rule(tx, &rule)?;
+    json(
+        &get_all_push_rules(tx)?
+            .into_iter()
+            .find(|v| v.target == target && v.target_id == target_id),
+    )
+}
+
+pub fn delete_push_rule(tx: &Transaction, target: &str, id: &str) -> Result<Response<Full<Bytes>>, common::PipeError> {
+    let target_id = match id.parse() {
+        Ok(v) => v,
+        Err(_) => return common::not_found(),
+    };
+    match push_rules::delete_push_rule(tx, target, target_id)? {
+        0 => common::not_found(),
+        _ => common::json_response("{}"),
+    }
+}
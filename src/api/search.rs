@@ -0,0 +1,148 @@
+use std::sync::LazyLock;
+
+use bytes::Bytes;
+use chrono::DateTime;
+use http::StatusCode;
+use http_body_util::Full;
+use hyper::Response;
+use quick_xml::{
+    Writer,
+    events::{BytesDecl, BytesText, Event},
+};
+use regex::Regex;
+use rusqlite::Transaction;
+use serde::Serialize;
+use url::form_urlencoded;
+
+use crate::api::json;
+use crate::common;
+use crate::storage::items;
+
+// tags, including the ones cut in half at both ends of a snippet
+static TAGS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[^<]*>|<[^>]*>|<[^>]*$").unwrap());
+
+#[derive(Serialize, Debug)]
+pub struct SearchResultApi {
+    id: u64,
+    feed_id: u64,
+    title: String,
+    author: String,
+    url: String,
+    published: u64,
+    snippet: String,
+    rank: f64,
+}
+
+struct SearchRequest {
+    query: String,
+    limit: u64,
+    offset: u64,
+}
+
+fn parse_search_request(query: &str) -> SearchRequest {
+    let mut request = SearchRequest {
+        query: String::new(),
+        limit: 20,
+        offset: 0,
+    };
+    for (k, v) in form_urlencoded::parse(query.as_bytes()) {
+        match k.as_ref() {
+            "q" => request.query = v.into_owned(),
+            "limit" => request.limit = v.parse().unwrap_or(request.limit).min(100),
+            "offset" => request.offset = v.parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+    request
+}
+
+fn clean_snippet(snippet: &str) -> String {
+    // matches are marked with control characters in sql, so they survive removing tags
+    TAGS.replace_all(snippet, "")
+        .replace('\x02', "<b>")
+        .replace('\x03', "</b>")
+}
+
+fn format_time(timestamp: u64) -> String {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .unwrap_or_default()
+        .to_rfc3339()
+}
+
+pub fn search(tx: &Transaction, query: &str) -> Result<Response<Full<Bytes>>, common::PipeError> {
+    let request = parse_search_request(query);
+    let results = items::search_items(tx, &request.query, request.limit, request.offset)?;
+    let results: Vec<SearchResultApi> = results
+        .into_iter()
+        .map(|result| SearchResultApi {
+            id: result.item.id,
+            feed_id: result.item.feed_id,
+            snippet: clean_snippet(&result.snippet),
+            rank: result.rank,
+            title: result.item.title,
+            author: result.item.author,
+            url: result.item.url,
+            published: result.item.created_on_time,
+        })
+        .collect();
+    json(&results)
+}
+
+/// Atom feed of search results, so a search can be subscribed to in feed readers
+pub fn search_feed(tx: &Transaction, query: &str) -> Result<Response<Full<Bytes>>, common::PipeError> {
+    let request = parse_search_request(query);
+    let results = items::search_items(tx, &request.query, request.limit, request.offset)?;
+    let updated = results.iter().map(|v| v.item.created_on_time).max().unwrap_or(0);
+    let title = format!("Search: {}", request.query);
+    let id = format!("urn:rss_pipe:search:{}", request.query);
+
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    writer
+        .create_element("feed")
+        .with_attribute(("xmlns", "http://www.w3.org/2005/Atom"))
+        .write_inner_content(|writer| {
+            writer
+                .create_element("title")
+                .write_text_content(BytesText::new(&title))?;
+            writer.create_element("id").write_text_content(BytesText::new(&id))?;
+            writer
+                .create_element("updated")
+                .write_text_content(BytesText::new(&format_time(updated)))?;
+            for result in &results {
+                let item = &result.item;
+                writer.create_element("entry").write_inner_content(|writer| {
+                    writer
+                        .create_element("title")
+                        .write_text_content(BytesText::new(&item.title))?;
+                    writer
+                        .create_element("id")
+                        .write_text_content(BytesText::new(&format!("urn:rss_pipe:item:{}", item.id)))?;
+                    writer
+                        .create_element("link")
+                        .with_attributes([("rel", "alternate"), ("href", item.url.as_str())])
+                        .write_empty()?;
+                    writer
+                        .create_element("updated")
+                        .write_text_content(BytesText::new(&format_time(item.created_on_time)))?;
+                    writer.create_element("author").write_inner_content(|writer| {
+                        writer
+                            .create_element("name")
+                            .write_text_content(BytesText::new(&item.author))?;
+                        Ok(())
+                    })?;
+                    writer
+                        .create_element("content")
+                        .with_attribute(("type", "html"))
+                        .write_text_content(BytesText::new(&item.html))?;
+                    Ok(())
+                })?;
+            }
+            Ok(())
+        })?;
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")
+        .body(Full::from(writer.into_inner()))
+        .map_err(|e| e.into())
+}
@@ -18,10 +18,7 @@ fn levenshtein_distance(str1: &str, str2: &str) -> usize {
         return s1_len;
     }
 
-    let mut v0: Vec<usize> = vec![0; s2_len + 1];
-    for i in 0..s2_len + 1 {
-        v0[i] = i
-    }
+    let mut v0: Vec<usize> = (0..s2_len + 1).collect();
 
     let mut v1: Vec<usize> = vec![0; s2_len + 1];
     for i in 0..s1_len {
@@ -35,9 +32,7 @@ fn levenshtein_distance(str1: &str, str2: &str) -> usize {
             let v = [v1[j] + 1, v0[j + 1] + 1, v0[j] + cost];
             v1[j + 1] = *v.iter().min().unwrap();
         }
-        for j in 0..s2_len + 1 {
-            v0[j] = v1[j]
-        }
+        v0.copy_from_slice(&v1);
     }
 
     v1[s2_len]
@@ -0,0 +1,153 @@
+use std::sync::{Arc, OnceLock};
+
+use pyo3::pymodule;
+use tokio::runtime::Handle;
+
+use crate::push;
+
+/// State shared with the `rss_pipe` module, set before the pipe script is loaded
+struct Host {
+    db: String,
+    push: Arc<push::Push>,
+    runtime: Handle,
+}
+
+static HOST: OnceLock<Host> = OnceLock::new();
+
+pub fn set(db: &str, push: Arc<push::Push>) {
+    let _ = HOST.set(Host {
+        db: db.to_owned(),
+        push,
+        runtime: Handle::current(),
+    });
+}
+
+/// Native module imported by the pipe script with `import rss_pipe`
+#[pymodule]
+pub mod rss_pipe {
+    use pyo3::exceptions::{PyRuntimeError, PyTypeError};
+    use pyo3::prelude::*;
+    use pyo3::types::{PyBytes, PyDict};
+    use rusqlite::types::Value;
+
+    use super::{HOST, Host};
+    use crate::push::Notification;
+    use crate::storage;
+
+    #[derive(IntoPyObject)]
+    struct HostItem {
+        id: u64,
+        feed_id: u64,
+        title: String,
+        content: String,
+        link: String,
+        author: String,
+        published: u64,
+        is_read: bool,
+        is_saved: bool,
+        counter: u64,
+    }
+
+    fn host() -> PyResult<&'static Host> {
+        HOST.get()
+            .ok_or_else(|| PyRuntimeError::new_err("rss_pipe host is not ready"))
+    }
+
+    fn to_value(v: &Bound<PyAny>) -> PyResult<Value> {
+        if v.is_none() {
+            Ok(Value::Null)
+        } else if let Ok(i) = v.extract::<i64>() {
+            Ok(Value::Integer(i))
+        } else if let Ok(f) = v.extract::<f64>() {
+            Ok(Value::Real(f))
+        } else if let Ok(s) = v.extract::<String>() {
+            Ok(Value::Text(s))
+        } else if let Ok(b) = v.cast::<PyBytes>() {
+            Ok(Value::Blob(b.as_bytes().to_vec()))
+        } else {
+            Err(PyTypeError::new_err(format!(
+                "unsupported query parameter type {}",
+                v.get_type()
+            )))
+        }
+    }
+
+    fn from_value(py: Python, v: Value) -> PyResult<Bound<PyAny>> {
+        Ok(match v {
+            Value::Null => py.None().into_bound(py),
+            Value::Integer(i) => i.into_pyobject(py)?.into_any(),
+            Value::Real(f) => f.into_pyobject(py)?.into_any(),
+            Value::Text(s) => s.into_pyobject(py)?.into_any(),
+            Value::Blob(b) => PyBytes::new(py, &b).into_any(),
+        })
+    }
+
+    /// Runs a read only query, returns rows as dicts of column names
+    #[pyfunction]
+    #[pyo3(signature = (sql, params=None))]
+    fn query<'py>(
+        py: Python<'py>,
+        sql: &str,
+        params: Option<Vec<Bound<'py, PyAny>>>,
+    ) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let host = host()?;
+        let params = params
+            .unwrap_or_default()
+            .iter()
+            .map(to_value)
+            .collect::<PyResult<Vec<Value>>>()?;
+        let (columns, rows) = py
+            .detach(|| storage::query_values(&host.db, sql, &params))
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        rows.into_iter()
+            .map(|row| {
+                let dict = PyDict::new(py);
+                for (column, value) in columns.iter().zip(row) {
+                    dict.set_item(column, from_value(py, value)?)?;
+                }
+                Ok(dict)
+            })
+            .collect()
+    }
+
+    #[pyfunction]
+    fn get_item(py: Python, id: u64) -> PyResult<Option<HostItem>> {
+        let host = host()?;
+        let items = py
+            .detach(|| {
+                storage::read_transaction(&host.db, |tx| {
+                    Ok(storage::items::get_items(tx, "with_ids", &id.to_string(), 1)?)
+                })
+            })
+            .map_err(|e| PyRuntimeError::new_err(format!("{e:?}")))?;
+        Ok(items.into_iter().next().map(|item| HostItem {
+            id: item.id,
+            feed_id: item.feed_id,
+            title: item.title,
+            content: item.html,
+            link: item.url,
+            author: item.author,
+            published: item.created_on_time,
+            is_read: item.is_read != 0,
+            is_saved: item.is_saved != 0,
+            counter: item.counter,
+        }))
+    }
+
+    /// Sends a push notification to all destinations set by `--push`
+    #[pyfunction]
+    #[pyo3(signature = (title, body, url=None))]
+    fn notify(title: &str, body: &str, url: Option<String>) -> PyResult<()> {
+        let host = host()?;
+        let notification = Notification::new(title, "", body, "", url, None);
+        let push = host.push.clone();
+        host.runtime
+            .spawn(async move { push.send_notification(notification).await });
+        Ok(())
+    }
+
+    #[pyfunction]
+    fn log(msg: &str) {
+        println!("pipe script: {msg}");
+    }
+}
@@ -8,8 +8,10 @@ use http::{Request, Response, StatusCode};
 use http_body_util::{BodyExt, Full};
 use hyper::body::Incoming;
 use hyper_socks2::TlsError;
+use rusqlite::ErrorCode;
 
 pub mod extract_content;
+pub mod host;
 pub mod script;
 
 #[derive(Debug)]
@@ -17,13 +19,48 @@ pub mod script;
 pub enum PipeError {
     InvalidHeaderValueError,
     UnsupportedSchemeError,
+    TooManyRedirectsError,
+    DecompressedTooLargeError,
+    PushStatusError(StatusCode),
+    PushTimeoutError,
+    ScriptTimeoutError,
+    JoinError(tokio::task::JoinError),
+    Database(rusqlite::Error),
+    DatabasePoolError(r2d2::Error),
+    DatabasePoolPoisonedError,
     HyperError(hyper::Error),
     HyperLegacyError(hyper_util::client::legacy::Error),
     InvalidUri(InvalidUri),
     HttpError(http::Error),
     SocksError(TlsError),
     FromUtf8Error(FromUtf8Error),
+    IoError(std::io::Error),
     SerdeError(serde_json::error::Error),
+    UrlParseError(url::ParseError),
+}
+
+impl PipeError {
+    pub fn is_database_error(&self) -> bool {
+        matches!(
+            self,
+            Self::Database(_) | Self::DatabasePoolError(_) | Self::DatabasePoolPoisonedError
+        )
+    }
+
+    /// Status code for database errors; the request may be retried when the database is busy
+    pub fn database_status_code(&self) -> Option<StatusCode> {
+        match self {
+            Self::Database(e) => match e.sqlite_error_code() {
+                Some(ErrorCode::ConstraintViolation) => Some(StatusCode::CONFLICT),
+                Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) => Some(StatusCode::SERVICE_UNAVAILABLE),
+                _ => Some(StatusCode::INTERNAL_SERVER_ERROR),
+            },
+            // every connection of the pool stays in use until the pool times out
+            Self::DatabasePoolError(_) => Some(StatusCode::SERVICE_UNAVAILABLE),
+            Self::DatabasePoolPoisonedError => Some(StatusCode::INTERNAL_SERVER_ERROR),
+            _ => None,
+        }
+    }
 }
 
 impl From<hyper::Error> for PipeError {
@@ -38,6 +75,24 @@ impl From<hyper_util::client::legacy::Error> for PipeError {
     }
 }
 
+impl From<tokio::task::JoinError> for PipeError {
+    fn from(err: tokio::task::JoinError) -> Self {
+        Self::JoinError(err)
+    }
+}
+
+impl From<rusqlite::Error> for PipeError {
+    fn from(err: rusqlite::Error) -> Self {
+        Self::Database(err)
+    }
+}
+
+impl From<r2d2::Error> for PipeError {
+    fn from(err: r2d2::Error) -> Self {
+        Self::DatabasePoolError(err)
+    }
+}
+
 impl From<TlsError> for PipeError {
     fn from(err: TlsError) -> Self {
         Self::SocksError(err)
@@ -68,12 +123,24 @@ impl From<FromUtf8Error> for PipeError {
     }
 }
 
+impl From<std::io::Error> for PipeError {
+    fn from(err: std::io::Error) -> Self {
+        Self::IoError(err)
+    }
+}
+
 impl From<serde_json::error::Error> for PipeError {
     fn from(value: serde_json::error::Error) -> Self {
         Self::SerdeError(value)
     }
 }
 
+impl From<url::ParseError> for PipeError {
+    fn from(value: url::ParseError) -> Self {
+        Self::UrlParseError(value)
+    }
+}
+
 pub async fn parse_request_body(req: Request<Incoming>) -> String {
     match req.into_body().collect().await {
         Ok(v) => String::from_utf8(v.to_bytes().to_vec()).unwrap_or_else(|e| {
@@ -112,6 +179,35 @@ pub fn json_response(v: &str) -> Result<Response<Full<Bytes>>, PipeError> {
         .map_err(|e| e.into())
 }
 
+pub fn text_response(v: &str) -> Result<Response<Full<Bytes>>, PipeError> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(Full::from(v.to_owned()))
+        .map_err(|e| e.into())
+}
+
+pub fn unauthorized() -> Result<Response<Full<Bytes>>, PipeError> {
+    Response::builder()
+        .status(StatusCode::UNAUTHORIZED)
+        .body(Full::from("unauthorized"))
+        .map_err(|e| e.into())
+}
+
+pub fn bad_request() -> Result<Response<Full<Bytes>>, PipeError> {
+    Response::builder()
+        .status(StatusCode::BAD_REQUEST)
+        .body(Full::from("bad request"))
+        .map_err(|e| e.into())
+}
+
+pub fn conflict() -> Result<Response<Full<Bytes>>, PipeError> {
+    Response::builder()
+        .status(StatusCode::CONFLICT)
+        .body(Full::from("conflict"))
+        .map_err(|e| e.into())
+}
+
 pub fn not_found() -> Result<Response<Full<Bytes>>, PipeError> {
     Response::builder()
         .status(StatusCode::NOT_FOUND)
@@ -119,6 +215,21 @@ pub fn not_found() -> Result<Response<Full<Bytes>>, PipeError> {
         .map_err(|e| e.into())
 }
 
+pub fn gateway_timeout() -> Result<Response<Full<Bytes>>, PipeError> {
+    Response::builder()
+        .status(StatusCode::GATEWAY_TIMEOUT)
+        .body(Full::from("gateway timeout"))
+        .map_err(|e| e.into())
+}
+
+pub fn service_unavailable() -> Result<Response<Full<Bytes>>, PipeError> {
+    Response::builder()
+        .status(StatusCode::SERVICE_UNAVAILABLE)
+        .header(http::header::RETRY_AFTER, "1")
+        .body(Full::from("service unavailable"))
+        .map_err(|e| e.into())
+}
+
 pub fn internal_server_error() -> Result<Response<Full<Bytes>>, PipeError> {
     Response::builder()
         .status(StatusCode::INTERNAL_SERVER_ERROR)
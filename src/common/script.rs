@@ -1,24 +1,77 @@
-use std::ffi::CString;
+use std::ffi::{CString, c_long};
 use std::fs::read_to_string;
 use std::path::Path;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
-use pyo3::prelude::{FromPyObject, Py, PyAny, PyModule, PyTracebackMethods, Python};
+use pyo3::PyErr;
+use pyo3::call::PyCallArgs;
+use pyo3::conversion::FromPyObjectOwned;
+use pyo3::prelude::{FromPyObject, IntoPyObject, Py, PyAny, PyAnyMethods, PyModule, PyTracebackMethods, Python};
+use pyo3::types::{PyDict, PyDictMethods};
 use pyo3_ffi::c_str;
+use tokio::sync::Semaphore;
+
+use crate::common::PipeError;
+use crate::common::host::rss_pipe;
+use crate::metrics;
+
+/// Number of script calls allowed to run on the blocking pool at the same time
+const WORKERS: usize = 4;
+const TIMED_OUT: i64 = -1;
+
+/// Item passed to `process_item(feed_url, item)` in the pipe script as a dict
+#[derive(Debug, Clone, Default, FromPyObject, IntoPyObject)]
+#[pyo3(from_item_all)]
+pub struct ScriptItem {
+    pub id: String,
+    pub title: String,
+    pub content: String,
+    pub link: String,
+    pub author: String,
+    pub published: u64,
+    #[pyo3(default)]
+    pub tags: Vec<String>,
+}
 
 pub struct Script {
     name: String,
-    module: Option<Py<PyModule>>,
+    module: RwLock<Option<Py<PyModule>>>,
+    timeout: Duration,
+    workers: Arc<Semaphore>,
 }
 
-impl Script {
-    pub fn empty() -> Self {
-        Self {
-            name: String::new(),
-            module: None,
-        }
+fn get_thread_ident(py: Python) -> i64 {
+    py.import("threading")
+        .and_then(|threading| threading.getattr("get_ident")?.call0()?.extract())
+        .unwrap_or(0)
+}
+
+/// Marks a timed out call, the ident is swapped while attached so the call can not start or finish meanwhile.
+/// Returns the number of calls still running after their timeout
+fn interrupt(thread_ident: &AtomicI64) -> Option<u64> {
+    Python::attach(|_| {
+        let ident = thread_ident.swap(TIMED_OUT, Ordering::SeqCst);
+        (ident > 0).then(|| {
+            // raises `TimeoutError` in the thread running the call as soon as it executes python code again
+            unsafe { pyo3_ffi::PyThreadState_SetAsyncExc(ident as c_long, pyo3_ffi::PyExc_TimeoutError) };
+            metrics::script_stuck()
+        })
+    })
+}
+
+fn format_error(py: Python, e: &PyErr) -> String {
+    match e.traceback(py).and_then(|f| f.format().ok()) {
+        Some(t) => format!("{t}{e}"),
+        None => e.to_string(),
     }
+}
 
+impl Script {
     pub fn initialize() {
+        pyo3::append_to_inittab!(rss_pipe);
         Python::initialize();
     }
 
@@ -26,53 +79,190 @@ impl Script {
         &self.name
     }
 
-    pub fn new(file_name: &str) -> Self {
-        let file_name_c_str = CString::new(file_name);
-        Self {
+    pub fn new(file_name: &str, timeout: &str) -> Self {
+        let script = Self {
             name: file_name.to_owned(),
-            module: read_to_string(Path::new(file_name)).ok().and_then(|script| {
-                Python::attach(|py| -> Option<Py<PyModule>> {
-                    PyModule::from_code(
-                        py,
-                        CString::new(script).ok()?.as_c_str(),
-                        file_name_c_str.ok()?.as_c_str(),
-                        c_str!(""), // todo: add later
-                    )
-                    .map(|m| m.unbind())
-                    .ok()
-                })
-            }),
+            module: RwLock::new(None),
+            timeout: Duration::from_secs(timeout.parse().unwrap_or(10)),
+            workers: Arc::new(Semaphore::new(WORKERS)),
+        };
+        if let Err(e) = script.reload() {
+            println!("!! error loading pipe script {file_name}:\n{e}");
         }
+        script
     }
 
-    pub fn getattr<T: for<'py> FromPyObject<'py, 'py>>(&self, v: &str) -> Option<T> {
-        self.module.as_ref().and_then(|module| {
-            Python::attach(|py| -> Option<T> {
-                let obj: Py<PyAny> = module.getattr(py, v).ok()?;
-                obj.extract(py).ok()
+    fn load(&self) -> Result<Py<PyModule>, String> {
+        let script = read_to_string(Path::new(&self.name)).map_err(|e| e.to_string())?;
+        let script_c_str = CString::new(script).map_err(|e| e.to_string())?;
+        let file_name_c_str = CString::new(self.name.as_str()).map_err(|e| e.to_string())?;
+        Python::attach(|py| {
+            PyModule::from_code(
+                py,
+                script_c_str.as_c_str(),
+                file_name_c_str.as_c_str(),
+                c_str!(""), // todo: add later
+            )
+            .map(|m| m.unbind())
+            .map_err(|e| format_error(py, &e))
+        })
+    }
+
+    /// Compiles the script again and swaps it in, the loaded module is kept if anything fails
+    pub fn reload(&self) -> Result<(), String> {
+        let module = self.load()?;
+        match self.module.write() {
+            Ok(mut v) => *v = Some(module),
+            Err(e) => return Err(e.to_string()),
+        }
+        Ok(())
+    }
+
+    fn module(&self, py: Python) -> Option<Py<PyModule>> {
+        // only hold the lock for cloning the reference, never while running python code
+        self.module
+            .read()
+            .ok()
+            .and_then(|v| v.as_ref().map(|m| m.clone_ref(py)))
+    }
+
+    pub fn has_function(&self, name: &str) -> bool {
+        Python::attach(|py| self.module(py).is_some_and(|module| module.getattr(py, name).is_ok()))
+    }
+
+    /// Runs `f` on the blocking pool so python code never blocks the server, `TimeoutError` is raised in
+    /// the call if it takes longer than the timeout
+    pub async fn call<T, F>(self: &Arc<Self>, function: &str, f: F) -> Result<T, PipeError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Script) -> T + Send + 'static,
+    {
+        let start_time = Instant::now();
+        let thread_ident = Arc::new(AtomicI64::new(0));
+        let running = async {
+            let permit = self.workers.clone().acquire_owned().await;
+            let script = self.clone();
+            let thread_ident = thread_ident.clone();
+            tokio::task::spawn_blocking(move || {
+                let _permit = permit;
+                Python::attach(|py| {
+                    let ident = get_thread_ident(py);
+                    if thread_ident
+                        .compare_exchange(0, ident, Ordering::SeqCst, Ordering::SeqCst)
+                        .is_err()
+                    {
+                        return None; // timed out before it started
+                    }
+                    let result = f(&script);
+                    // a timeout raised after `f` returned must not leak into the next call on this thread
+                    if thread_ident.swap(0, Ordering::SeqCst) == TIMED_OUT {
+                        unsafe { pyo3_ffi::PyThreadState_SetAsyncExc(ident as c_long, null_mut()) };
+                        metrics::script_unstuck();
+                    }
+                    Some(result)
+                })
             })
+            .await
+        };
+        let result = match tokio::time::timeout(self.timeout, running).await {
+            Ok(Ok(Some(v))) => Ok(v),
+            Ok(Ok(None)) => Err(PipeError::ScriptTimeoutError),
+            Ok(Err(e)) => Err(e.into()),
+            Err(_) => {
+                println!(
+                    "!! {function} in pipe script timed out after {}s",
+                    self.timeout.as_secs()
+                );
+                tokio::task::spawn_blocking(move || {
+                    // a script catching `TimeoutError` keeps its worker until it returns
+                    if let Some(stuck) = interrupt(&thread_ident) {
+                        println!("!! {stuck} pipe script calls still running after timing out, of {WORKERS} workers");
+                    }
+                });
+                Err(PipeError::ScriptTimeoutError)
+            }
+        };
+        metrics::script_call(function, start_time.elapsed(), result.is_err());
+        result
+    }
+
+    pub fn getattr<T: for<'py> FromPyObject<'py, 'py>>(&self, v: &str) -> Option<T> {
+        Python::attach(|py| -> Option<T> {
+            let obj: Py<PyAny> = self.module(py)?.getattr(py, v).ok()?;
+            obj.extract(py).ok()
         })
     }
 
-    pub fn evaluate(&self, a: &str, b: &str, c: &str, return_traceback: bool) -> Option<String> {
-        self.module.as_ref().and_then(|module| {
-            Python::attach(|py| -> Option<String> {
-                let app: Py<PyAny> = module.getattr(py, format!("{}_{}", a, b)).ok()?; // Great API design in pyo3!
-                match app.call1(py, (c,)) {
-                    Ok(v) => Some(v.to_string()),
-                    Err(e) => {
-                        if return_traceback {
-                            match e.traceback(py).and_then(|f| f.format().ok()) {
-                                Some(t) => Some(format!("{t}{e}")),
-                                None => Some(e.to_string()),
-                            }
-                        } else {
-                            e.print(py);
-                            None
-                        }
+    /// Reads a dict (in insertion order) or a list of pairs as key value pairs
+    pub fn getattr_items<T: for<'py> FromPyObjectOwned<'py>>(&self, v: &str) -> Option<Vec<T>> {
+        Python::attach(|py| -> Option<Vec<T>> {
+            let obj = self.module(py)?.getattr(py, v).ok()?.into_bound(py);
+            match obj.cast::<PyDict>() {
+                Ok(dict) => dict.items().extract().ok(),
+                Err(_) => obj.extract().ok(),
+            }
+        })
+    }
+
+    pub fn evaluate<A: for<'py> PyCallArgs<'py>>(
+        &self,
+        a: &str,
+        b: &str,
+        args: A,
+        return_traceback: bool,
+    ) -> Option<String> {
+        Python::attach(|py| -> Option<String> {
+            let app: Py<PyAny> = self.module(py)?.getattr(py, format!("{}_{}", a, b)).ok()?; // Great API design in pyo3!
+            match app.call1(py, args) {
+                Ok(v) => Some(v.to_string()),
+                Err(e) => {
+                    if return_traceback {
+                        Some(format_error(py, &e))
+                    } else {
+                        e.print(py);
+                        None
                     }
                 }
-            })
+            }
+        })
+    }
+
+    /// Returns `None` if the item is dropped by returning `False`; the item is kept unchanged on errors
+    pub fn process_item(&self, feed_url: &str, item: ScriptItem) -> Option<ScriptItem> {
+        Python::attach(|py| {
+            let module = match self.module(py) {
+                Some(v) => v,
+                None => return Some(item),
+            };
+            let process_item = match module.getattr(py, "process_item") {
+                Ok(v) => v,
+                Err(_) => return Some(item),
+            };
+            let item_dict = match item.clone().into_pyobject(py) {
+                Ok(v) => v,
+                Err(e) => {
+                    e.print(py);
+                    return Some(item);
+                }
+            };
+            // the dict can be modified in place and returned as `None`, or replaced by a returned dict
+            let processed = match process_item.bind(py).call1((feed_url, &item_dict)) {
+                Ok(v) if v.is_none() => item_dict.extract::<ScriptItem>(),
+                Ok(v) if v.extract::<bool>().is_ok_and(|keep| !keep) => return None,
+                Ok(v) => v.extract::<ScriptItem>(),
+                Err(e) => Err(e),
+            };
+            match processed {
+                Ok(v) => Some(v),
+                Err(e) => {
+                    println!(
+                        "!! error processing item {} of {feed_url}, keeping it unchanged",
+                        item.id
+                    );
+                    e.print(py);
+                    Some(item)
+                }
+            }
         })
     }
 }
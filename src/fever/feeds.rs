@@ -1,7 +1,7 @@
 use rusqlite::Transaction;
 use serde::Serialize;
 
-use crate::storage::feeds;
+use crate::storage::{favicons, feeds};
 
 #[derive(Serialize, Debug)]
 pub struct FeedFever {
@@ -14,22 +14,40 @@ pub struct FeedFever {
     last_updated_on_time: u64,
 }
 
-pub fn get_all_feeds(tx: &Transaction) -> Vec<FeedFever> {
-    feeds::get_all_feeds(tx)
-        .unwrap_or_default()
+pub fn get_all_feeds(tx: &Transaction) -> Result<Vec<FeedFever>, rusqlite::Error> {
+    let all_feeds = feeds::get_all_feeds(tx)?
         .iter()
         .map(|(feed, feed_url)| FeedFever {
             id: feed.id,
             favicon_id: feed.id,
             title: feed.title.to_owned(),
             url: feed_url.url.to_owned(),
-            site_url: String::new(),
+            site_url: feed.site_url.to_owned(),
             is_spark: 0,
             last_updated_on_time: feed.last_updated,
         })
-        .collect()
+        .collect();
+    Ok(all_feeds)
 }
 
-pub fn get_last_refreshed_time(tx: &Transaction) -> u64 {
+pub fn get_last_refreshed_time(tx: &Transaction) -> Result<u64, rusqlite::Error> {
     feeds::get_last_refreshed_time(tx)
 }
+
+#[derive(Serialize, Debug)]
+pub struct FaviconFever {
+    id: u64,
+    data: String,
+}
+
+pub fn get_all_favicons(tx: &Transaction) -> Result<Vec<FaviconFever>, rusqlite::Error> {
+    let all_favicons = favicons::get_all_favicons(tx)?
+        .iter()
+        .map(|favicon| FaviconFever {
+            id: favicon.feed_id,
+            // icons saved by older versions still carry the data: scheme
+            data: favicon.data.trim_start_matches("data:").to_owned(),
+        })
+        .collect();
+    Ok(all_favicons)
+}
@@ -0,0 +1,50 @@
+use std::collections::BTreeMap;
+
+use rusqlite::Transaction;
+use serde::Serialize;
+
+use crate::storage::groups;
+
+#[derive(Serialize, Debug)]
+pub struct GroupFever {
+    id: u64,
+    title: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FeedsGroupsFever {
+    group_id: u64,
+    feed_ids: String,
+}
+
+pub fn get_all_groups(tx: &Transaction) -> Result<Vec<GroupFever>, rusqlite::Error> {
+    let all_groups = groups::get_all_groups(tx)?
+        .iter()
+        .map(|group| GroupFever {
+            id: group.id,
+            title: group.title.to_owned(),
+        })
+        .collect();
+    Ok(all_groups)
+}
+
+pub fn get_feeds_groups(tx: &Transaction) -> Result<String, rusqlite::Error> {
+    let mut feed_ids: BTreeMap<u64, Vec<String>> = BTreeMap::new();
+    for feed_group in groups::get_all_feed_groups(tx)? {
+        feed_ids
+            .entry(feed_group.group_id)
+            .or_default()
+            .push(feed_group.feed_id.to_string());
+    }
+    let feeds_groups: Vec<FeedsGroupsFever> = feed_ids
+        .into_iter()
+        .map(|(group_id, ids)| FeedsGroupsFever {
+            group_id,
+            feed_ids: ids.join(","),
+        })
+        .collect();
+    Ok(format!(
+        ", \"feeds_groups\": {}",
+        serde_json::to_string(&feeds_groups).unwrap_or("[]".to_owned())
+    ))
+}
@@ -1,41 +1,77 @@
 use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use rusqlite::Transaction;
 
 use crate::storage::items;
 
-pub fn get_items(tx: &Transaction, actions: &HashMap<String, String>) -> Vec<items::Item> {
+pub fn get_items(
+    tx: &Transaction,
+    actions: &HashMap<String, String>,
+    limit: u64,
+) -> Result<Vec<items::Item>, rusqlite::Error> {
     if let Some(with_ids) = actions.get("with_ids") {
-        items::get_items(tx, "with_ids", &with_ids.replace("%2C", ",")).unwrap_or_default()
+        items::get_items(tx, "with_ids", &with_ids.replace("%2C", ","), limit)
     } else if let Some(since_id) = actions.get("since_id") {
-        items::get_items(tx, "since_id", since_id).unwrap_or_default()
+        items::get_items(tx, "since_id", since_id, limit)
+    } else if let Some(max_id) = actions.get("max_id") {
+        items::get_items(tx, "max_id", max_id, limit)
     } else {
-        vec![]
+        Ok(vec![])
     }
 }
 
-pub fn get_total_items(tx: &Transaction) -> String {
-    format!(", \"total_items\": {}", items::get_total_items(tx, ""))
+pub fn get_total_items(tx: &Transaction) -> Result<String, rusqlite::Error> {
+    Ok(format!(", \"total_items\": {}", items::get_total_items(tx, "")?))
 }
 
-pub fn get_unread_item_ids(tx: &Transaction) -> String {
-    let ids = items::get_unread_item_ids(tx).unwrap_or_default();
+pub fn get_unread_item_ids(tx: &Transaction) -> Result<String, rusqlite::Error> {
+    let ids = items::get_unread_item_ids(tx)?;
     let ids_str: Vec<String> = ids.iter().map(|x| x.to_string()).collect();
-    ids_str.join(",")
+    Ok(ids_str.join(","))
 }
 
-pub fn get_saved_item_ids(tx: &Transaction) -> String {
-    let ids = items::get_saved_item_ids(tx).unwrap_or_default();
+pub fn get_saved_item_ids(tx: &Transaction) -> Result<String, rusqlite::Error> {
+    let ids = items::get_saved_item_ids(tx)?;
     let ids_str: Vec<String> = ids.iter().map(|x| x.to_string()).collect();
-    ids_str.join(",")
+    Ok(ids_str.join(","))
 }
 
-pub fn mark(tx: &Transaction, id: &str, kind: &str) {
+pub fn mark(tx: &Transaction, id: &str, kind: &str) -> Result<usize, rusqlite::Error> {
     match kind {
         "read" => items::set_item_read_status(tx, id, "1"),
         "saved" => items::set_item_saved_status(tx, id, "1"),
         "unread" => items::set_item_read_status(tx, id, "0"),
         "unsaved" => items::set_item_saved_status(tx, id, "0"),
-        _ => {}
+        _ => Ok(0),
+    }
+}
+
+fn get_before(before: Option<&String>) -> u64 {
+    before.and_then(|v| v.parse().ok()).unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::from_secs(0))
+            .as_secs()
+    })
+}
+
+pub fn mark_feed(tx: &Transaction, id: &str, kind: &str, before: Option<&String>) -> Result<usize, rusqlite::Error> {
+    match (id.parse(), kind) {
+        (Ok(feed_id), "read") => items::set_items_read_status_before(tx, "feed", feed_id, get_before(before)),
+        _ => Ok(0),
+    }
+}
+
+pub fn mark_group(tx: &Transaction, id: &str, kind: &str, before: Option<&String>) -> Result<usize, rusqlite::Error> {
+    match (id.parse::<i64>(), kind) {
+        // group 0 is the kindling super group containing every feed
+        (Ok(0), "read") => items::set_items_read_status_before(tx, "all", 0, get_before(before)),
+        // group -1 is the sparks super group, there are no spark feeds yet so nothing to mark
+        (Ok(-1), "read") => Ok(0),
+        (Ok(group_id), "read") if group_id > 0 => {
+            items::set_items_read_status_before(tx, "group", group_id as u64, get_before(before))
+        }
+        _ => Ok(0),
     }
 }
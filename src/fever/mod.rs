@@ -9,6 +9,7 @@ use serde::Serialize;
 use crate::{common, storage};
 
 mod feeds;
+mod groups;
 mod items;
 
 async fn parse_request_actions(req: Request<Incoming>) -> HashMap<String, String> {
@@ -34,55 +35,72 @@ fn return_with_base_response<T: Serialize>(
     };
     let result = format!(
         "{{\"api_version\": 3, \"auth\": 1, \"last_refreshed_on_time\": {}{}{}}}",
-        feeds::get_last_refreshed_time(tx),
+        feeds::get_last_refreshed_time(tx)?,
         kv_part,
         a
     );
     common::json_response(&result)
 }
 
-pub async fn fever(db: &str, auth: &str, req: Request<Incoming>) -> Result<Response<Full<Bytes>>, common::PipeError> {
+pub async fn fever(
+    db: &str,
+    auth: &str,
+    items_limit: u64,
+    req: Request<Incoming>,
+) -> Result<Response<Full<Bytes>>, common::PipeError> {
     let empty = Vec::<u8>::new();
     let actions = parse_request_actions(req).await;
     if let Some(api_key) = actions.get("api_key") {
         if api_key.to_lowercase() == auth {
-            return storage::transaction(db, |tx| {
+            // only marking items writes, everything else is served from a read transaction
+            let write = actions.contains_key("mark");
+            return storage::transaction_for(db, write, |tx| {
                 if actions.contains_key("feeds") {
-                    return return_with_base_response(tx, "feeds", &feeds::get_all_feeds(tx), "");
+                    return return_with_base_response(
+                        tx,
+                        "feeds",
+                        &feeds::get_all_feeds(tx)?,
+                        &groups::get_feeds_groups(tx)?,
+                    );
                 }
                 if actions.contains_key("items") {
                     return return_with_base_response(
                         tx,
                         "items",
-                        &items::get_items(tx, &actions),
-                        &items::get_total_items(tx),
+                        &items::get_items(tx, &actions, items_limit)?,
+                        &items::get_total_items(tx)?,
+                    );
+                }
+                if actions.contains_key("groups") {
+                    return return_with_base_response(
+                        tx,
+                        "groups",
+                        &groups::get_all_groups(tx)?,
+                        &groups::get_feeds_groups(tx)?,
                     );
                 }
                 if actions.contains_key("unread_item_ids") {
-                    return return_with_base_response(tx, "unread_item_ids", &items::get_unread_item_ids(tx), "");
+                    return return_with_base_response(tx, "unread_item_ids", &items::get_unread_item_ids(tx)?, "");
                 }
                 if actions.contains_key("saved_item_ids") {
-                    return return_with_base_response(tx, "saved_item_ids", &items::get_saved_item_ids(tx), "");
+                    return return_with_base_response(tx, "saved_item_ids", &items::get_saved_item_ids(tx)?, "");
                 }
                 // unimplemented read operations
                 if actions.contains_key("links") {
                     return return_with_base_response(tx, "links", &empty, "");
                 }
-                if actions.contains_key("groups") {
-                    return return_with_base_response(tx, "groups", &empty, "");
-                }
                 if actions.contains_key("favicons") {
-                    return return_with_base_response(tx, "favicons", &empty, "");
+                    return return_with_base_response(tx, "favicons", &feeds::get_all_favicons(tx)?, "");
                 }
                 // write operations
-                if let Some(mark) = actions.get("mark") {
-                    if let Some(kind) = actions.get("as") {
-                        if let Some(id) = actions.get("id") {
-                            if mark == "item" {
-                                items::mark(tx, id, kind)
-                            }
-                        }
-                    }
+                if let (Some(mark), Some(kind), Some(id)) = (actions.get("mark"), actions.get("as"), actions.get("id"))
+                {
+                    match mark.as_str() {
+                        "item" => items::mark(tx, id, kind)?,
+                        "feed" => items::mark_feed(tx, id, kind, actions.get("before"))?,
+                        "group" => items::mark_group(tx, id, kind, actions.get("before"))?,
+                        _ => 0,
+                    };
                 }
                 // default handler
                 return_with_base_response(tx, "", &Vec::<u8>::new(), "")
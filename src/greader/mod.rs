@@ -0,0 +1,141 @@
+use bytes::Bytes;
+use http::HeaderMap;
+use http_body_util::Full;
+use hyper::{Request, Response, body::Incoming};
+use openssl::hash::{MessageDigest, hash};
+use percent_encoding::percent_decode_str;
+use serde::Serialize;
+use url::form_urlencoded;
+
+use crate::{common, storage};
+
+mod streams;
+mod subscriptions;
+
+const READING_LIST: &str = "user/-/state/com.google/reading-list";
+const READ: &str = "user/-/state/com.google/read";
+const STARRED: &str = "user/-/state/com.google/starred";
+const KEPT_UNREAD: &str = "user/-/state/com.google/kept-unread";
+const LABEL_PREFIX: &str = "user/-/label/";
+const ITEM_PREFIX: &str = "tag:google.com,2005:reader/item/";
+
+pub struct Params(Vec<(String, String)>);
+
+impl Params {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    pub fn get_all(&self, key: &str) -> Vec<&str> {
+        self.0
+            .iter()
+            .filter(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+            .collect()
+    }
+}
+
+async fn parse_request_params(req: Request<Incoming>) -> Params {
+    let query_string = req.uri().query().unwrap_or("").to_owned();
+    let body = common::parse_request_body(req).await;
+    Params(
+        form_urlencoded::parse(query_string.as_bytes())
+            .into_owned()
+            .chain(form_urlencoded::parse(body.as_bytes()).into_owned())
+            .collect(),
+    )
+}
+
+fn get_auth_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(http::header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("GoogleLogin auth=")
+}
+
+fn md5_hex(input: &str) -> Option<String> {
+    let digest = hash(MessageDigest::md5(), input.as_bytes()).ok()?;
+    Some(digest.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+fn client_login(auth: &str, params: &Params) -> Result<Response<Full<Bytes>>, common::PipeError> {
+    let email = params.get("Email").unwrap_or("");
+    let password = params.get("Passwd").unwrap_or("");
+    // same convention as the fever api key: md5("email:password"), or the key itself as password
+    if password == auth || md5_hex(&format!("{email}:{password}")).as_deref() == Some(auth) {
+        common::text_response(&format!("SID={auth}\nLSID={auth}\nAuth={auth}\n"))
+    } else {
+        println!("!! greader login failed for {email}");
+        common::unauthorized()
+    }
+}
+
+fn normalize_stream(stream: &str) -> String {
+    // clients may use their user id instead of `-`
+    match stream.strip_prefix("user/").and_then(|s| s.split_once('/')) {
+        Some((_, rest)) => format!("user/-/{rest}"),
+        None => stream.to_owned(),
+    }
+}
+
+fn parse_item_id(v: &str) -> Option<u64> {
+    match v.strip_prefix(ITEM_PREFIX) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => v.parse().ok(),
+    }
+}
+
+fn json<T: Serialize>(v: &T) -> Result<Response<Full<Bytes>>, common::PipeError> {
+    common::json_response(&serde_json::to_string(v)?)
+}
+
+pub async fn greader(
+    db: &str,
+    auth: Option<&str>,
+    path: &str,
+    req: Request<Incoming>,
+) -> Result<Response<Full<Bytes>>, common::PipeError> {
+    let Some(auth) = auth else {
+        return common::unauthorized();
+    };
+    let authorized = get_auth_token(req.headers()) == Some(auth);
+    let params = parse_request_params(req).await;
+    if path == "accounts/ClientLogin" {
+        return client_login(auth, &params);
+    }
+    if !authorized {
+        return common::unauthorized();
+    }
+    let write = matches!(path, "reader/api/0/edit-tag" | "reader/api/0/mark-all-as-read");
+    storage::transaction_for(db, write, |tx| match path {
+        "reader/api/0/token" => common::text_response(auth),
+        "reader/api/0/user-info" => json(&subscriptions::get_user_info()),
+        "reader/api/0/subscription/list" => json(&subscriptions::get_subscriptions(tx)?),
+        "reader/api/0/tag/list" => json(&subscriptions::get_tags(tx)?),
+        "reader/api/0/unread-count" => json(&subscriptions::get_unread_counts(tx)?),
+        "reader/api/0/stream/items/ids" => match streams::get_item_ids(tx, &params)? {
+            Some(v) => json(&v),
+            None => common::not_found(),
+        },
+        "reader/api/0/stream/items/contents" => json(&streams::get_items_by_id(tx, &params)?),
+        "reader/api/0/edit-tag" => {
+            streams::edit_tag(tx, &params)?;
+            common::text_response("OK")
+        }
+        "reader/api/0/mark-all-as-read" => {
+            streams::mark_all_as_read(tx, &params)?;
+            common::text_response("OK")
+        }
+        _ => match path.strip_prefix("reader/api/0/stream/contents") {
+            Some(stream_path) => {
+                let stream = percent_decode_str(stream_path.trim_start_matches('/')).decode_utf8_lossy();
+                match streams::get_stream_contents(tx, &stream, &params)? {
+                    Some(v) => json(&v),
+                    None => common::not_found(),
+                }
+            }
+            None => common::not_found(),
+        },
+    })
+}
@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rusqlite::Transaction;
+use serde::Serialize;
+
+use crate::greader::{
+    ITEM_PREFIX, KEPT_UNREAD, LABEL_PREFIX, Params, READ, READING_LIST, STARRED, normalize_stream, parse_item_id,
+};
+use crate::storage::{feeds, groups, items, tags};
+
+const DEFAULT_COUNT: u64 = 20;
+const MAX_COUNT: u64 = 10000;
+
+#[derive(Serialize, Debug)]
+pub struct Link {
+    href: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    kind: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Content {
+    direction: String,
+    content: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Origin {
+    stream_id: String,
+    title: String,
+    html_url: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemGReader {
+    id: String,
+    crawl_time_msec: String,
+    timestamp_usec: String,
+    published: u64,
+    updated: u64,
+    title: String,
+    author: String,
+    canonical: Vec<Link>,
+    alternate: Vec<Link>,
+    summary: Content,
+    categories: Vec<String>,
+    origin: Origin,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamContents {
+    id: String,
+    updated: u64,
+    items: Vec<ItemGReader>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    continuation: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemRef {
+    id: String,
+    timestamp_usec: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemRefs {
+    item_refs: Vec<ItemRef>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    continuation: Option<String>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::from_secs(0))
+        .as_secs()
+}
+
+fn apply_stream(filter: &mut items::ItemFilter, stream: &str) -> Option<()> {
+    let stream = normalize_stream(stream);
+    if stream == READING_LIST {
+    } else if stream == STARRED {
+        filter.is_saved = Some(1);
+    } else if stream == READ {
+        filter.is_read = Some(1);
+    } else if let Some(feed_id) = stream.strip_prefix("feed/") {
+        filter.feed_id = Some(feed_id.parse().ok()?);
+    } else if let Some(label) = stream.strip_prefix(LABEL_PREFIX) {
+        filter.label = Some(label.to_owned());
+    } else {
+        println!("!! unsupported greader stream: {stream}");
+        return None;
+    }
+    Some(())
+}
+
+fn get_item_filter(stream: &str, params: &Params) -> Option<items::ItemFilter> {
+    let mut filter = items::ItemFilter {
+        limit: params
+            .get("n")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_COUNT)
+            .min(MAX_COUNT),
+        offset: params.get("c").and_then(|v| v.parse().ok()).unwrap_or(0),
+        newer_than: params.get("nt").and_then(|v| v.parse().ok()),
+        older_than: params.get("ot").and_then(|v| v.parse().ok()),
+        oldest_first: params.get("r") == Some("o"),
+        ..Default::default()
+    };
+    apply_stream(&mut filter, stream)?;
+    for exclude in params.get_all("xt") {
+        match normalize_stream(exclude).as_str() {
+            READ => filter.is_read = Some(0),
+            STARRED => filter.is_saved = Some(0),
+            _ => {}
+        }
+    }
+    for include in params.get_all("it") {
+        match normalize_stream(include).as_str() {
+            READ => filter.is_read = Some(1),
+            STARRED => filter.is_saved = Some(1),
+            _ => {}
+        }
+    }
+    Some(filter)
+}
+
+fn get_continuation(filter: &items::ItemFilter, count: usize) -> Option<String> {
+    if count as u64 >= filter.limit && filter.limit > 0 {
+        Some((filter.offset + filter.limit).to_string())
+    } else {
+        None
+    }
+}
+
+fn to_greader_items(tx: &Transaction, items: Vec<items::Item>) -> Result<Vec<ItemGReader>, rusqlite::Error> {
+    let feeds: HashMap<u64, feeds::Feed> = feeds::get_all_feeds(tx)?
+        .into_iter()
+        .map(|(feed, _)| (feed.id, feed))
+        .collect();
+    let labels = groups::get_feed_group_titles(tx)?;
+    let item_ids: Vec<u64> = items.iter().map(|item| item.id).collect();
+    let item_tags = tags::get_item_tags(tx, &item_ids)?;
+    let greader_items = items
+        .into_iter()
+        .map(|item| {
+            let mut categories = vec![READING_LIST.to_owned()];
+            if item.is_read == 1 {
+                categories.push(READ.to_owned());
+            }
+            if item.is_saved == 1 {
+                categories.push(STARRED.to_owned());
+            }
+            for label in labels.get(&item.feed_id).map(|v| v.as_slice()).unwrap_or_default() {
+                categories.push(format!("{LABEL_PREFIX}{label}"));
+            }
+            for tag in item_tags.get(&item.id).map(|v| v.as_slice()).unwrap_or_default() {
+                categories.push(format!("{LABEL_PREFIX}{tag}"));
+            }
+            let feed = feeds.get(&item.feed_id);
+            ItemGReader {
+                id: format!("{ITEM_PREFIX}{:016x}", item.id),
+                crawl_time_msec: (item.created_on_time * 1000).to_string(),
+                timestamp_usec: (item.created_on_time * 1_000_000).to_string(),
+                published: item.created_on_time,
+                updated: item.created_on_time,
+                title: item.title,
+                author: item.author,
+                canonical: vec![Link {
+                    href: item.url.to_owned(),
+                    kind: None,
+                }],
+                alternate: vec![Link {
+                    href: item.url,
+                    kind: Some("text/html".to_owned()),
+                }],
+                summary: Content {
+                    direction: "ltr".to_owned(),
+                    content: item.html,
+                },
+                categories,
+                origin: Origin {
+                    stream_id: format!("feed/{}", item.feed_id),
+                    title: feed.map(|f| f.title.to_owned()).unwrap_or_default(),
+                    html_url: feed.map(|f| f.site_url.to_owned()).unwrap_or_default(),
+                },
+            }
+        })
+        .collect();
+    Ok(greader_items)
+}
+
+pub fn get_stream_contents(
+    tx: &Transaction,
+    stream_path: &str,
+    params: &Params,
+) -> Result<Option<StreamContents>, rusqlite::Error> {
+    let stream = if stream_path.is_empty() {
+        params.get("s").unwrap_or(READING_LIST)
+    } else {
+        stream_path
+    };
+    let filter = match get_item_filter(stream, params) {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    let items = items::get_filtered_items(tx, &filter)?;
+    Ok(Some(StreamContents {
+        id: stream.to_owned(),
+        updated: now(),
+        continuation: get_continuation(&filter, items.len()),
+        items: to_greader_items(tx, items)?,
+    }))
+}
+
+pub fn get_item_ids(tx: &Transaction, params: &Params) -> Result<Option<ItemRefs>, rusqlite::Error> {
+    let filter = match get_item_filter(params.get("s").unwrap_or(READING_LIST), params) {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    let ids = items::get_filtered_item_ids(tx, &filter)?;
+    Ok(Some(ItemRefs {
+        continuation: get_continuation(&filter, ids.len()),
+        item_refs: ids
+            .into_iter()
+            .map(|(id, created_on_time)| ItemRef {
+                id: id.to_string(),
+                timestamp_usec: (created_on_time * 1_000_000).to_string(),
+            })
+            .collect(),
+    }))
+}
+
+pub fn get_items_by_id(tx: &Transaction, params: &Params) -> Result<StreamContents, rusqlite::Error> {
+    let ids: Vec<String> = params
+        .get_all("i")
+        .into_iter()
+        .filter_map(parse_item_id)
+        .map(|id| id.to_string())
+        .collect();
+    let items = if ids.is_empty() {
+        vec![]
+    } else {
+        items::get_items(tx, "with_ids", &ids.join(","), ids.len() as u64)?
+    };
+    Ok(StreamContents {
+        id: READING_LIST.to_owned(),
+        updated: now(),
+        continuation: None,
+        items: to_greader_items(tx, items)?,
+    })
+}
+
+pub fn edit_tag(tx: &Transaction, params: &Params) -> Result<(), rusqlite::Error> {
+    let ids: Vec<String> = params
+        .get_all("i")
+        .into_iter()
+        .filter_map(parse_item_id)
+        .map(|id| id.to_string())
+        .collect();
+    for (tags, added) in [(params.get_all("a"), true), (params.get_all("r"), false)] {
+        for tag in tags {
+            for id in &ids {
+                match (normalize_stream(tag).as_str(), added) {
+                    (READ, true) | (KEPT_UNREAD, false) => items::set_item_read_status(tx, id, "1")?,
+                    (READ, false) | (KEPT_UNREAD, true) => items::set_item_read_status(tx, id, "0")?,
+                    (STARRED, true) => items::set_item_saved_status(tx, id, "1")?,
+                    (STARRED, false) => items::set_item_saved_status(tx, id, "0")?,
+                    _ => 0,
+                };
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn mark_all_as_read(tx: &Transaction, params: &Params) -> Result<usize, rusqlite::Error> {
+    // `ts` is in microseconds
+    let before = params
+        .get("ts")
+        .and_then(|v| v.parse::<u64>().ok())
+        .map_or_else(now, |v| v / 1_000_000);
+    let stream = normalize_stream(params.get("s").unwrap_or(READING_LIST));
+    if stream == READING_LIST {
+        return items::set_items_read_status_before(tx, "all", 0, before);
+    }
+    if let Some(feed_id) = stream.strip_prefix("feed/").and_then(|v| v.parse().ok()) {
+        return items::set_items_read_status_before(tx, "feed", feed_id, before);
+    }
+    if let Some(title) = stream.strip_prefix(LABEL_PREFIX)
+        && let Some(group_id) = groups::get_group_id_by_title(tx, title)?
+    {
+        return items::set_items_read_status_before(tx, "group", group_id, before);
+    }
+    Ok(0)
+}
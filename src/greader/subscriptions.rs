@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use rusqlite::Transaction;
+use serde::Serialize;
+
+use crate::greader::{LABEL_PREFIX, READING_LIST, STARRED};
+use crate::storage::{self, feeds, groups, items};
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UserInfo {
+    user_id: String,
+    user_name: String,
+    user_profile_id: String,
+    user_email: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Category {
+    id: String,
+    label: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Subscription {
+    id: String,
+    title: String,
+    categories: Vec<Category>,
+    url: String,
+    html_url: String,
+    icon_url: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SubscriptionList {
+    subscriptions: Vec<Subscription>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Tag {
+    id: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    kind: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TagList {
+    tags: Vec<Tag>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UnreadCount {
+    id: String,
+    count: u64,
+    newest_item_timestamp_usec: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct UnreadCounts {
+    max: u64,
+    unreadcounts: Vec<UnreadCount>,
+}
+
+pub fn get_user_info() -> UserInfo {
+    UserInfo {
+        user_id: "1".to_owned(),
+        user_name: "rss_pipe".to_owned(),
+        user_profile_id: "1".to_owned(),
+        user_email: String::new(),
+    }
+}
+
+pub fn get_subscriptions(tx: &Transaction) -> Result<SubscriptionList, rusqlite::Error> {
+    let labels = groups::get_feed_group_titles(tx)?;
+    Ok(SubscriptionList {
+        subscriptions: feeds::get_all_feeds(tx)?
+            .iter()
+            .map(|(feed, feed_url)| Subscription {
+                id: format!("feed/{}", feed.id),
+                title: feed.title.to_owned(),
+                categories: labels
+                    .get(&feed.id)
+                    .map(|v| {
+                        v.iter()
+                            .map(|label| Category {
+                                id: format!("{LABEL_PREFIX}{label}"),
+                                label: label.to_owned(),
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                url: feed_url.url.to_owned(),
+                html_url: feed.site_url.to_owned(),
+                icon_url: String::new(),
+            })
+            .collect(),
+    })
+}
+
+pub fn get_tags(tx: &Transaction) -> Result<TagList, rusqlite::Error> {
+    let mut tags = vec![Tag {
+        id: STARRED.to_owned(),
+        kind: None,
+    }];
+    for group in groups::get_all_groups(tx)? {
+        tags.push(Tag {
+            id: format!("{LABEL_PREFIX}{}", group.title),
+            kind: Some("folder".to_owned()),
+        });
+    }
+    for tag in storage::tags::get_all_tags(tx)? {
+        tags.push(Tag {
+            id: format!("{LABEL_PREFIX}{tag}"),
+            kind: Some("tag".to_owned()),
+        });
+    }
+    Ok(TagList { tags })
+}
+
+pub fn get_unread_counts(tx: &Transaction) -> Result<UnreadCounts, rusqlite::Error> {
+    let labels = groups::get_feed_group_titles(tx)?;
+    let mut unreadcounts = vec![];
+    let mut label_counts: HashMap<&str, (u64, u64)> = HashMap::new();
+    let (mut total, mut newest) = (0, 0);
+    for (feed_id, count, newest_item) in items::get_unread_counts(tx)? {
+        for label in labels.get(&feed_id).map(|v| v.as_slice()).unwrap_or_default() {
+            let entry = label_counts.entry(label).or_default();
+            *entry = (entry.0 + count, entry.1.max(newest_item));
+        }
+        total += count;
+        newest = newest.max(newest_item);
+        unreadcounts.push(UnreadCount {
+            id: format!("feed/{feed_id}"),
+            count,
+            newest_item_timestamp_usec: (newest_item * 1_000_000).to_string(),
+        });
+    }
+    for (label, (count, newest_item)) in label_counts {
+        unreadcounts.push(UnreadCount {
+            id: format!("{LABEL_PREFIX}{label}"),
+            count,
+            newest_item_timestamp_usec: (newest_item * 1_000_000).to_string(),
+        });
+    }
+    unreadcounts.push(UnreadCount {
+        id: READING_LIST.to_owned(),
+        count: total,
+        newest_item_timestamp_usec: (newest * 1_000_000).to_string(),
+    });
+    Ok(UnreadCounts {
+        max: total,
+        unreadcounts,
+    })
+}
@@ -1,13 +1,21 @@
 #![deny(unused_extern_crates)]
-use std::{collections::HashMap, error::Error, net::SocketAddr, sync::OnceLock, time::Instant};
+use std::{
+    collections::HashMap,
+    error::Error,
+    net::SocketAddr,
+    sync::{Arc, OnceLock},
+    time::Instant,
+};
 
 use bytes::Bytes;
 use http_body_util::Full;
-use hyper::{Request, Response, body::Incoming, server::conn::http1::Builder, service::service_fn};
+use hyper::{Request, Response, StatusCode, body::Incoming, server::conn::http1::Builder, service::service_fn};
 use tokio::net::TcpListener;
 
+mod api;
 mod common;
 mod fever;
+mod greader;
 mod metrics;
 mod pipe;
 mod push;
@@ -19,8 +27,12 @@ static METRICS: OnceLock<metrics::Metrics> = OnceLock::new();
 static VALINE: OnceLock<valine::Valine> = OnceLock::new();
 static PIPE: OnceLock<pipe::Pipe> = OnceLock::new();
 
+#[allow(clippy::too_many_arguments)]
 async fn handle(
     path: &str,
+    auth: Option<&str>,
+    prefix: &str,
+    items_limit: u64,
     pipe: &pipe::Pipe,
     valine: &valine::Valine,
     metrics: &metrics::Metrics,
@@ -38,7 +50,13 @@ async fn handle(
     } else if req_path.starts_with("/1.1/classes/Counter") {
         valine.handle_counter(req).await
     } else if req_path.starts_with(&format!("/{path}/fever")) {
-        fever::fever(db, fever_auth, req).await
+        fever::fever(db, fever_auth, items_limit, req).await
+    } else if let Some(greader_path) = req_path.strip_prefix(&format!("/{path}/greader/")) {
+        greader::greader(db, auth, greader_path, req).await
+    } else if req_path == format!("/{path}/api/reload") {
+        api::reload(auth, pipe, req).await
+    } else if let Some(api_path) = req_path.strip_prefix(&format!("/{path}/api/")) {
+        api::api(db, auth, prefix, api_path, req).await
     } else if req_path.starts_with(&format!("/{path}/statistics/")) {
         metrics.handle_statistics(req).await
     } else if let Some(feed) = req_path.strip_prefix("/http/") {
@@ -52,8 +70,12 @@ async fn handle(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_wrapper(
     path: &str,
+    auth: Option<&str>,
+    prefix: &str,
+    items_limit: u64,
     pipe: &pipe::Pipe,
     valine: &valine::Valine,
     metrics: &metrics::Metrics,
@@ -62,7 +84,7 @@ async fn handle_wrapper(
 ) -> Result<Response<Full<Bytes>>, String> {
     let start_time = Instant::now();
     let req_info = format!("accepted {} {} {}", remote_addr, req.method(), req.uri());
-    let response = handle(path, pipe, valine, metrics, req).await;
+    let response = handle(path, auth, prefix, items_limit, pipe, valine, metrics, req).await;
     match response {
         Ok(r) => {
             println!(
@@ -73,7 +95,24 @@ async fn handle_wrapper(
             );
             Ok(r)
         }
-        Err(e) => Err(format!("{e:?}")),
+        Err(e) => match e.database_status_code() {
+            Some(status) => {
+                println!("!! database error: {e:?}");
+                println!(
+                    "{} {} {}ms",
+                    req_info,
+                    status.as_u16(),
+                    start_time.elapsed().as_millis()
+                );
+                match status {
+                    StatusCode::CONFLICT => common::conflict(),
+                    StatusCode::SERVICE_UNAVAILABLE => common::service_unavailable(),
+                    _ => common::internal_server_error(),
+                }
+                .map_err(|e| format!("{e:?}"))
+            }
+            None => Err(format!("{e:?}")),
+        },
     }
 }
 
@@ -98,10 +137,24 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
         Some(v) => v,
         None => "not set (please set --auth in order to use fever api)",
     };
+    // the management api and greader stay locked unless --auth is set explicitly
+    let api_auth = m.get("--auth").map(String::as_str);
     let args_bark = match m.get("--bark") {
         Some(v) => v,
         None => "",
     };
+    let args_push = match m.get("--push") {
+        Some(v) => v,
+        None => "",
+    };
+    let args_batch = match m.get("--batch") {
+        Some(v) => v,
+        None => "0",
+    };
+    let args_digest = match m.get("--digest") {
+        Some(v) => v,
+        None => "",
+    };
     let args_bind = match m.get("--bind") {
         Some(v) => v,
         None => "172.17.0.1:5080",
@@ -122,29 +175,108 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
         Some(v) => v,
         None => "https://example.com/",
     };
+    let args_items = match m.get("--items") {
+        Some(v) => v,
+        None => "50",
+    };
+    let args_redirects = match m.get("--redirects") {
+        Some(v) => v,
+        None => "5",
+    };
+    let args_favicon = match m.get("--favicon") {
+        Some(v) => v,
+        None => "604800",
+    };
+    let args_interval = match m.get("--interval") {
+        Some(v) => v,
+        None => "0",
+    };
+    let args_jitter = match m.get("--jitter") {
+        Some(v) => v,
+        None => "60",
+    };
+    let args_concurrency = match m.get("--concurrency") {
+        Some(v) => v,
+        None => "4",
+    };
+    let args_timeout = match m.get("--timeout") {
+        Some(v) => v,
+        None => "10",
+    };
+    let args_max_age = match m.get("--max-age") {
+        Some(v) => v,
+        None => "0",
+    };
+    let args_max_items = match m.get("--max-items") {
+        Some(v) => v,
+        None => "0",
+    };
+    let args_updates = match m.get("--updates") {
+        Some(v) => v,
+        None => "update",
+    };
 
     let addr: SocketAddr = args_bind.parse()?;
+    let items_limit: u64 = match args_items.parse()? {
+        0 => return Err("--items must be greater than 0".into()),
+        v => v,
+    };
+    let max_age: u64 = args_max_age.parse()?;
+    let max_items: u64 = args_max_items.parse()?;
 
-    storage::migrations(args_db);
+    storage::migrations(args_db)?;
 
     common::script::Script::initialize();
 
-    let pipe_script = common::script::Script::new(args_pipe);
-    let statistics: Option<Vec<String>> = pipe_script.getattr("statistics");
-    let metrics_instance = METRICS.get_or_init(|| metrics::Metrics::new(args_db, statistics));
-    let pipe_instance = PIPE.get_or_init(|| pipe::Pipe::new(args_db, args_bark, args_proxy, pipe_script));
-    let valine_instance = VALINE.get_or_init(|| valine::Valine::new(args_db, args_auth, args_bark, args_path));
+    let push_instance = Arc::new(push::Push::new(args_db, args_push, args_bark, args_batch));
+    common::host::set(args_db, push_instance.clone());
+    let pipe_script = Arc::new(common::script::Script::new(args_pipe, args_timeout));
+    let schedule: Option<HashMap<String, u64>> = pipe_script.getattr("schedule");
+    let scheduler = pipe::scheduler::Scheduler::new(args_interval, args_jitter, args_concurrency, schedule);
+    tokio::spawn(push::outbox::run(push_instance.clone()));
+    tokio::spawn(pipe::retention::Retention::new(max_age, max_items).run(args_db.to_owned()));
+    if let Some(at) = push::rules::parse_time(args_digest) {
+        tokio::spawn(push::digest::run(push_instance.clone(), args_db.to_owned(), at));
+    }
+    let metrics_instance = METRICS.get_or_init(|| metrics::Metrics::new(args_db, pipe_script.clone()));
+    let pipe_instance = PIPE.get_or_init(|| {
+        pipe::Pipe::new(
+            args_db,
+            push_instance.clone(),
+            args_proxy,
+            args_redirects,
+            args_favicon,
+            args_updates,
+            scheduler,
+            pipe_script,
+        )
+    });
+    tokio::spawn(pipe_instance.reload_on_hangup());
+    let valine_instance = VALINE.get_or_init(|| valine::Valine::new(args_db, args_auth, push_instance, args_path));
 
     println!(
         "Running with args (set with --key=value):\n \
         --db: {args_db}\n \
         --auth: {args_auth}\n \
         --bark: {args_bark}\n \
+        --push: {args_push}\n \
+        --batch: {args_batch}\n \
+        --digest: {args_digest}\n \
         --bind: {args_bind}\n \
         --path: {args_path}\n \
+        --items: {args_items}\n \
         --pipe: {args_pipe}\n \
+        --timeout: {args_timeout}\n \
         --proxy: {args_proxy}\n \
-        --prefix: {args_prefix}"
+        --prefix: {args_prefix}\n \
+        --redirects: {args_redirects}\n \
+        --favicon: {args_favicon}\n \
+        --interval: {args_interval}\n \
+        --jitter: {args_jitter}\n \
+        --concurrency: {args_concurrency}\n \
+        --max-age: {args_max_age}\n \
+        --max-items: {args_max_items}\n \
+        --updates: {args_updates}"
     );
 
     let listener = TcpListener::bind(addr).await?;
@@ -153,6 +285,9 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
         let service = service_fn(move |req| {
             handle_wrapper(
                 args_path,
+                api_auth,
+                args_prefix,
+                items_limit,
                 pipe_instance,
                 valine_instance,
                 metrics_instance,
@@ -1,10 +1,14 @@
+use std::collections::BTreeMap;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use bytes::Bytes;
 use http::{StatusCode, header};
 use http_body_util::Full;
 use hyper::{Request, Response, body::Incoming};
 
+use crate::common::script::Script;
 use crate::{common, storage};
 
 static GLOBAL_HTTP_200: AtomicU64 = AtomicU64::new(0);
@@ -12,6 +16,17 @@ static GLOBAL_HTTP_304: AtomicU64 = AtomicU64::new(0);
 static GLOBAL_HTTP_502: AtomicU64 = AtomicU64::new(0);
 static GLOBAL_HTTP_503: AtomicU64 = AtomicU64::new(0);
 static GLOBAL_PIPE_ERR: AtomicU64 = AtomicU64::new(0);
+static GLOBAL_DB_ERR: AtomicU64 = AtomicU64::new(0);
+static GLOBAL_PRUNED: AtomicU64 = AtomicU64::new(0);
+static GLOBAL_SCRIPT_STUCK: AtomicU64 = AtomicU64::new(0);
+static SCRIPT_CALLS: Mutex<BTreeMap<String, ScriptCalls>> = Mutex::new(BTreeMap::new());
+
+#[derive(Default)]
+struct ScriptCalls {
+    count: u64,
+    timeouts: u64,
+    seconds: f64,
+}
 
 pub fn status_code_200() {
     GLOBAL_HTTP_200.fetch_add(1, Ordering::Relaxed);
@@ -33,38 +48,101 @@ pub fn pipe_error() {
     GLOBAL_PIPE_ERR.fetch_add(1, Ordering::Relaxed);
 }
 
+pub fn database_error() {
+    GLOBAL_DB_ERR.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn items_pruned(count: u64) {
+    GLOBAL_PRUNED.fetch_add(count, Ordering::Relaxed);
+}
+
+/// Counts a script call still running after its timeout, returns the current count
+pub fn script_stuck() -> u64 {
+    GLOBAL_SCRIPT_STUCK.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+pub fn script_unstuck() {
+    GLOBAL_SCRIPT_STUCK.fetch_sub(1, Ordering::Relaxed);
+}
+
+pub fn script_call(function: &str, elapsed: Duration, timed_out: bool) {
+    if let Ok(mut calls) = SCRIPT_CALLS.lock() {
+        let calls = calls.entry(function.to_owned()).or_default();
+        calls.count += 1;
+        calls.timeouts += timed_out as u64;
+        calls.seconds += elapsed.as_secs_f64();
+    }
+}
+
+fn format_script_calls() -> String {
+    let calls = match SCRIPT_CALLS.lock() {
+        Ok(v) => v,
+        Err(_) => return String::new(),
+    };
+    calls
+        .iter()
+        .map(|(function, v)| {
+            format!(
+                "rss_pipe_script_call_count{{function=\"{function}\"}} {}\n\
+                rss_pipe_script_call_seconds_sum{{function=\"{function}\"}} {}\n\
+                rss_pipe_script_timeout_count{{function=\"{function}\"}} {}\n",
+                v.count, v.seconds, v.timeouts,
+            )
+        })
+        .collect()
+}
+
 pub struct Metrics {
     db: String,
-    statistics: Option<Vec<String>>,
+    script: Arc<Script>,
 }
 
 impl Metrics {
-    pub fn new(db: &str, statistics: Option<Vec<String>>) -> Self {
+    pub fn new(db: &str, script: Arc<Script>) -> Self {
         Self {
             db: db.to_owned(),
-            statistics,
+            script,
         }
     }
 
     pub async fn handle_metrics(&self) -> Result<Response<Full<Bytes>>, common::PipeError> {
-        let metrics_value = storage::transaction(&self.db, |tx| {
-            let unread_count = storage::items::get_total_items(tx, "where is_read = 0");
-            format!(
-                "# RSS Pipe Metrics\n\
-                rss_pipe_status_code_count{{status_code=\"200\"}} {}\n\
-                rss_pipe_status_code_count{{status_code=\"304\"}} {}\n\
-                rss_pipe_status_code_count{{status_code=\"502\"}} {}\n\
-                rss_pipe_status_code_count{{status_code=\"503\"}} {}\n\
-                rss_pipe_error_count{{}} {}\n\
-                rss_pipe_unread_count{{}} {}\n",
-                GLOBAL_HTTP_200.load(Ordering::Relaxed),
-                GLOBAL_HTTP_304.load(Ordering::Relaxed),
-                GLOBAL_HTTP_502.load(Ordering::Relaxed),
-                GLOBAL_HTTP_503.load(Ordering::Relaxed),
-                GLOBAL_PIPE_ERR.load(Ordering::Relaxed),
-                unread_count,
-            )
+        // counters kept in memory are still reported when the database can not be read
+        let database_value = storage::read_transaction(&self.db, |tx| {
+            let unread_count = storage::items::get_total_items(tx, "where is_read = 0")?;
+            let (push_pending, push_failed) = storage::push_outbox::get_outbox_counts(tx)?;
+            Ok(format!(
+                "rss_pipe_unread_count{{}} {}\n\
+                rss_pipe_push_outbox_count{{status=\"pending\"}} {}\n\
+                rss_pipe_push_outbox_count{{status=\"failed\"}} {}\n",
+                unread_count, push_pending, push_failed,
+            ))
+        })
+        .unwrap_or_else(|e| {
+            println!("!! error reading metrics from database: {e:?}");
+            String::new()
         });
+        let metrics_value = format!(
+            "# RSS Pipe Metrics\n\
+            rss_pipe_status_code_count{{status_code=\"200\"}} {}\n\
+            rss_pipe_status_code_count{{status_code=\"304\"}} {}\n\
+            rss_pipe_status_code_count{{status_code=\"502\"}} {}\n\
+            rss_pipe_status_code_count{{status_code=\"503\"}} {}\n\
+            rss_pipe_error_count{{}} {}\n\
+            rss_pipe_database_error_count{{}} {}\n\
+            rss_pipe_pruned_item_count{{}} {}\n\
+            rss_pipe_script_stuck_count{{}} {}\n\
+            {}{}",
+            GLOBAL_HTTP_200.load(Ordering::Relaxed),
+            GLOBAL_HTTP_304.load(Ordering::Relaxed),
+            GLOBAL_HTTP_502.load(Ordering::Relaxed),
+            GLOBAL_HTTP_503.load(Ordering::Relaxed),
+            GLOBAL_PIPE_ERR.load(Ordering::Relaxed),
+            GLOBAL_DB_ERR.load(Ordering::Relaxed),
+            GLOBAL_PRUNED.load(Ordering::Relaxed),
+            GLOBAL_SCRIPT_STUCK.load(Ordering::Relaxed),
+            database_value,
+            format_script_calls(),
+        );
         Response::builder()
             .header(
                 header::CONTENT_TYPE,
@@ -82,7 +160,8 @@ impl Metrics {
             Err(_) => return common::not_found(),
         };
 
-        let statistics = match &self.statistics {
+        // read from the script on every request so reloading the pipe script takes effect
+        let statistics: Vec<String> = match self.script.getattr("statistics") {
             Some(v) => v,
             None => return common::not_found(),
         };
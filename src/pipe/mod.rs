@@ -1,28 +1,54 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use base64::{Engine, prelude::BASE64_STANDARD};
 use bytes::{Buf, Bytes};
 use http::{Method, header};
-use http_body_util::{BodyExt, Full};
+use http_body_util::{BodyExt, Full, Limited};
 use hyper::{Request, Response, StatusCode, body::Incoming};
+use regex::Regex;
+use tokio::signal::unix::{SignalKind, signal};
 use tokio::sync::mpsc::{Sender, channel};
+use url::Url;
 
+use crate::common::script::ScriptItem;
 use crate::{common, metrics, push, storage};
 
 mod proxy;
+pub mod retention;
+pub mod scheduler;
+
+const FAVICON_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_FAVICON_SIZE: usize = 1024 * 1024;
 
 struct ParseRequest {
     url: String,
     body: Bytes,
+    headers: HashMap<String, String>,
     query: Option<String>,
+    moved_to: Option<String>,
     status_code: StatusCode,
 }
 
+// handling of items fetched again with a changed title or content
+#[derive(Clone, Copy, PartialEq)]
+enum Updates {
+    Ignore,
+    Update,
+    Unread,
+}
+
 pub struct Pipe {
     db: String,
-    bark: String,
+    push: Arc<push::Push>,
     proxy: String,
+    redirects: u8,
+    favicon: u64,
+    updates: Updates,
     sender: Sender<ParseRequest>,
-    methods: common::script::Script,
+    methods: Arc<common::script::Script>,
+    transforms: Arc<RwLock<Vec<(Regex, String)>>>,
 }
 
 fn handle_error(uri: &str, message: String) -> String {
@@ -31,116 +57,391 @@ fn handle_error(uri: &str, message: String) -> String {
     message
 }
 
+fn get_accept_encoding<B>(req: &Request<B>) -> Option<String> {
+    req.headers()
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_owned())
+}
+
+fn load_transforms(methods: &common::script::Script) -> Vec<(Regex, String)> {
+    // `transforms` in the pipe script maps feed url patterns to `transform_{name}(body, headers)` functions,
+    // the first matching pattern wins so the order of the script is kept
+    let transforms: Vec<(String, String)> = methods.getattr_items("transforms").unwrap_or_default();
+    transforms
+        .into_iter()
+        .filter_map(|(pattern, name)| match Regex::new(&pattern) {
+            Ok(v) => Some((v, name)),
+            Err(e) => {
+                println!("!! invalid transform pattern {pattern}: {e}");
+                None
+            }
+        })
+        .collect()
+}
+
 impl Pipe {
-    pub fn new(db: &str, bark: &str, proxy: &str, methods: common::script::Script) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        db: &str,
+        push: Arc<push::Push>,
+        proxy: &str,
+        redirects: &str,
+        favicon: &str,
+        updates: &str,
+        scheduler: scheduler::Scheduler,
+        methods: Arc<common::script::Script>,
+    ) -> Self {
         let (sender, mut receiver) = channel(1024);
-
-        let consumer = Self {
-            bark: bark.to_owned(),
+        let pipe = Self {
             db: db.to_owned(),
-            methods: common::script::Script::empty(),
+            push,
+            transforms: Arc::new(RwLock::new(load_transforms(&methods))),
+            methods,
             proxy: proxy.to_owned(),
-            sender: sender.clone(),
+            redirects: redirects.parse().unwrap_or(0),
+            favicon: favicon.parse().unwrap_or(0),
+            updates: match updates {
+                "ignore" => Updates::Ignore,
+                "unread" => Updates::Unread,
+                _ => Updates::Update,
+            },
+            sender,
         };
 
+        if scheduler.is_enabled() {
+            tokio::spawn(scheduler.run(Arc::new(pipe.fork())));
+        }
+
+        let consumer = pipe.fork();
         tokio::spawn(async move {
             loop {
                 if let Some(p) = &receiver.recv().await {
-                    match feed_rs::parser::parse(p.body.clone().reader()) {
-                        Ok(feed) => consumer.handle_feed(&p.url, &p.query, feed).await,
+                    let body = consumer.transform(p).await;
+                    match feed_rs::parser::parse(body.reader()) {
+                        Ok(feed) => consumer.handle_feed(&p.url, &p.query, &p.moved_to, feed).await,
                         Err(v) => consumer.handle_feed_error(p, v).await,
                     }
                 }
             }
         });
+        pipe
+    }
+
+    fn fork(&self) -> Self {
         Self {
-            bark: bark.to_owned(),
-            db: db.to_owned(),
-            methods,
-            proxy: proxy.to_owned(),
-            sender,
+            db: self.db.to_owned(),
+            push: self.push.clone(),
+            methods: self.methods.clone(),
+            transforms: self.transforms.clone(),
+            proxy: self.proxy.to_owned(),
+            redirects: self.redirects,
+            favicon: self.favicon,
+            updates: self.updates,
+            sender: self.sender.clone(),
+        }
+    }
+
+    /// Reloads the pipe script, returns the traceback and keeps the loaded one if it fails
+    pub fn reload(&self) -> Result<(), String> {
+        if let Err(e) = self.methods.reload() {
+            println!("!! error reloading pipe script {}:\n{e}", self.methods.get_name());
+            return Err(e);
+        }
+        let transforms = load_transforms(&self.methods);
+        if let Ok(mut v) = self.transforms.write() {
+            *v = transforms;
+        }
+        println!("reloaded pipe script {}", self.methods.get_name());
+        Ok(())
+    }
+
+    pub async fn reload_on_hangup(&self) {
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(v) => v,
+            Err(e) => {
+                println!("!! error listening for SIGHUP: {e}");
+                return;
+            }
+        };
+        while hangup.recv().await.is_some() {
+            let _ = self.reload();
         }
     }
 
-    async fn handle_feed(&self, url: &str, query: &Option<String>, feed: feed_rs::model::Feed) {
+    async fn transform(&self, p: &ParseRequest) -> Bytes {
+        // empty 304 bodies and error pages are not feeds to fix
+        if p.status_code != StatusCode::OK {
+            return p.body.clone();
+        }
+        let full_url = match &p.query {
+            Some(v) => format!("{}?{}", p.url, v),
+            None => p.url.to_owned(),
+        };
+        let name = match self.transforms.read().ok().and_then(|transforms| {
+            transforms
+                .iter()
+                .find(|(pattern, _)| pattern.is_match(&full_url))
+                .map(|(_, name)| name.to_owned())
+        }) {
+            Some(v) => v,
+            None => return p.body.clone(),
+        };
+        let args = (String::from_utf8_lossy(&p.body).into_owned(), p.headers.clone());
+        let function = format!("transform_{name}");
+        match self
+            .methods
+            .call(&function, move |script| {
+                script.evaluate("transform", &name, args, false)
+            })
+            .await
+        {
+            Ok(Some(v)) => {
+                println!("transformed body of {full_url} with {function}");
+                Bytes::from(v)
+            }
+            _ => {
+                println!("!! error transforming body of {full_url} with {function}, parsing it unchanged");
+                p.body.clone()
+            }
+        }
+    }
+
+    async fn handle_feed(
+        &self,
+        url: &str,
+        query: &Option<String>,
+        moved_to: &Option<String>,
+        feed: feed_rs::model::Feed,
+    ) {
         let full_url = match query {
             Some(v) => format!("{}?{}", url, v),
             None => url.to_owned(),
         };
         let feed_title = feed.title.map_or_else(String::new, |title| title.content.to_owned());
-        let bark_requests = storage::transaction(&self.db, |tx| {
+        let site_url = feed
+            .links
+            .iter()
+            .find(|link| !matches!(link.rel.as_deref(), Some("self") | Some("hub")))
+            .map(|link| link.href.to_owned());
+        let icon_url = feed
+            .icon
+            .as_ref()
+            .or(feed.logo.as_ref())
+            .map(|image| image.uri.to_owned())
+            .or_else(|| {
+                let site = Url::parse(site_url.as_deref()?).ok()?;
+                site.join("/favicon.ico").ok().map(|u| u.to_string())
+            });
+        // items are processed by `process_item` in the pipe script (if defined) before saving
+        let process_item = self.methods.has_function("process_item");
+        let mut items: Vec<ScriptItem> = vec![];
+        for item in feed.entries.iter().rev() {
+            let created_at = match item.published {
+                Some(published) => published.timestamp(),
+                None => item.updated.map_or(0, |updated| updated.timestamp()),
+            };
+            let script_item = ScriptItem {
+                id: item.id.to_owned(),
+                title: item.title.as_ref().map_or("", |title| &title.content).to_owned(),
+                content: match &item.content {
+                    Some(content) => content.body.as_deref().unwrap_or(""),
+                    None => item.summary.as_ref().map_or("", |summary| &summary.content),
+                }
+                .to_owned(),
+                link: item.links.first().map_or("", |a| &a.href).to_owned(),
+                author: item
+                    .authors
+                    .first()
+                    .map_or("", |a| a.email.as_ref().unwrap_or(&a.name))
+                    .to_owned(),
+                published: match created_at {
+                    0 => SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or(Duration::from_secs(0))
+                        .as_secs(),
+                    _ => created_at as u64,
+                },
+                tags: vec![],
+            };
+            if !process_item {
+                items.push(script_item);
+                continue;
+            }
+            let (url, unchanged) = (full_url.to_owned(), script_item.clone());
+            match self
+                .methods
+                .call("process_item", move |script| script.process_item(&url, script_item))
+                .await
+            {
+                Ok(Some(v)) => items.push(v),
+                Ok(None) => println!("dropping item {} by process_item", item.id),
+                Err(e) => {
+                    println!(
+                        "!! error processing item {} of {full_url}, keeping it unchanged: {e:?}",
+                        item.id
+                    );
+                    items.push(unchanged);
+                }
+            }
+        }
+        let result = storage::transaction(&self.db, |tx| {
             let mut bark_requests: Vec<(&str, &str, &str, &str)> = Vec::new();
-            let (feed_id, url_id, feed_created) = storage::feeds::upsert_feed(tx, &full_url, Some(&feed_title));
+            let (feed_id, url_id, feed_created) = storage::feeds::upsert_feed(tx, &full_url, Some(&feed_title))?;
             if feed_created {
                 bark_requests.push(("New Feed Subscription", "", &feed_title, ""));
                 println!("creating new feed {feed_title} [{feed_id}] {full_url} [{url_id}]");
             }
-            if feed_id > 0 && url_id > 0 {
-                for item in feed.entries.iter().rev() {
-                    let item_title = item.title.as_ref().map_or("", |title| &title.content);
-                    let content = match &item.content {
-                        Some(content) => content.body.as_deref().unwrap_or(""),
-                        None => item.summary.as_ref().map_or("", |summary| &summary.content),
-                    };
-                    let link = item.links.first().map_or("", |a| &a.href);
-                    let author = item.authors.first().map_or("", |a| a.email.as_ref().unwrap_or(&a.name));
-                    let created_at = match item.published {
-                        Some(published) => published.timestamp(),
-                        None => item.updated.map_or(0, |updated| updated.timestamp()),
-                    };
-                    let created_at_valid = match created_at {
-                        0 => SystemTime::now()
-                            .duration_since(UNIX_EPOCH)
-                            .unwrap_or(Duration::from_secs(0))
-                            .as_secs(),
-                        _ => created_at as u64,
-                    };
-                    let (item_id_update, item_updated) = storage::valine::refresh_existing_item(
+            if let Some(site) = &site_url {
+                storage::feeds::set_site_url(tx, feed_id, site)?;
+            }
+            if let Some(moved) = moved_to
+                && storage::feeds::add_feed_url(tx, feed_id, moved)?
+            {
+                println!("feed {feed_title} [{feed_id}] moved permanently to {moved}");
+            }
+            let push_rules = storage::push_rules::get_push_rules(tx, feed_id)?;
+            let now = chrono::Local::now().time();
+            for item in &items {
+                if storage::retention::is_pruned(tx, feed_id, &item.id)? {
+                    continue;
+                }
+                let item_id_update = storage::valine::refresh_existing_item(
+                    tx,
+                    feed_id,
+                    &item.id,
+                    &item.title,
+                    &item.content,
+                    &item.link,
+                    &item.author,
+                    item.published,
+                )?;
+                let notify = push::rules::should_notify(&push_rules, &item.title, &item.content, now);
+                if let Some(item_id_update) = item_id_update {
+                    println!("updating existing item {} [{}]", item.id, item_id_update);
+                    storage::tags::set_item_tags(tx, item_id_update, &item.tags)?;
+                    if notify {
+                        bark_requests.push((&feed_title, &item.title, &item.content, &item.link));
+                    }
+                } else {
+                    let (item_id, item_created) = storage::items::create_item(
                         tx,
                         feed_id,
                         &item.id,
-                        item_title,
-                        content,
-                        link,
-                        author,
-                        created_at_valid,
-                    );
-                    if item_updated {
-                        println!("updating existing item {} [{}]", item.id, item_id_update);
-                        bark_requests.push((&feed_title, item_title, content, link));
-                    } else {
-                        let (item_id, item_created) = storage::items::create_item(
-                            tx,
-                            feed_id,
-                            &item.id,
-                            item_title,
-                            content,
-                            link,
-                            author,
-                            created_at_valid,
-                        );
-                        if item_created {
-                            println!("creating new item {} [{}]", item.id, item_id);
-                            if !feed_created {
-                                bark_requests.push((&feed_title, item_title, content, link));
-                            }
+                        &item.title,
+                        &item.content,
+                        &item.link,
+                        &item.author,
+                        item.published,
+                    )?;
+                    if item_created {
+                        println!("creating new item {} [{}]", item.id, item_id);
+                        storage::tags::set_item_tags(tx, item_id, &item.tags)?;
+                        if !feed_created && notify {
+                            bark_requests.push((&feed_title, &item.title, &item.content, &item.link));
                         }
+                    } else if self.updates != Updates::Ignore
+                        && storage::items::update_item(
+                            tx,
+                            item_id,
+                            &item.title,
+                            &item.content,
+                            self.updates == Updates::Unread,
+                        )?
+                    {
+                        println!("updating changed item {} [{}]", item.id, item_id);
+                        storage::tags::set_item_tags(tx, item_id, &item.tags)?;
                     }
                 }
             }
-            bark_requests
+            let refresh_favicon = self.favicon > 0 && storage::favicons::needs_refresh(tx, feed_id, self.favicon)?;
+            Ok((bark_requests, feed_id, refresh_favicon))
         });
+        let (bark_requests, feed_id, refresh_favicon) = match result {
+            Ok(v) => v,
+            Err(e) => {
+                metrics::pipe_error();
+                println!("!! error saving feed {full_url}: {e:?}");
+                return;
+            }
+        };
+        if let Some(icon) = icon_url
+            && refresh_favicon
+        {
+            // a slow or huge icon must not hold up parsing of other feeds
+            let (db, proxy, redirects) = (self.db.to_owned(), self.proxy.to_owned(), self.redirects);
+            tokio::spawn(async move {
+                let refresh = Self::refresh_favicon(&db, &proxy, redirects, feed_id, &icon);
+                if tokio::time::timeout(FAVICON_TIMEOUT, refresh).await.is_err() {
+                    println!("!! timed out refreshing favicon {icon}");
+                }
+            });
+        }
         for request in bark_requests {
-            push::bark::send_notification(
-                request.0,
-                request.1,
-                request.2,
-                "rss_pipe_rust",
-                Some(request.3.to_owned()),
-                None,
-                &self.bark,
-            )
-            .await;
+            self.push
+                .queue_notification(
+                    feed_id,
+                    push::Notification::new(
+                        request.0,
+                        request.1,
+                        request.2,
+                        "rss_pipe_rust",
+                        Some(request.3.to_owned()),
+                        None,
+                    ),
+                )
+                .await;
+        }
+    }
+
+    async fn refresh_favicon(db: &str, proxy: &str, redirects: u8, feed_id: u64, icon_url: &str) {
+        // failures are saved as empty data too, so the icon is not fetched again until it expires
+        let data = match proxy::http_https_get(icon_url, proxy, redirects).await {
+            Ok(fetched) if fetched.response.status() == StatusCode::OK => {
+                let (parts, incoming) = fetched.response.into_parts();
+                let content_type = parts
+                    .headers
+                    .get(header::CONTENT_TYPE)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.split(';').next())
+                    .map(|v| v.trim().to_lowercase())
+                    .unwrap_or_default();
+                // sites without an icon often answer with an html page instead of a 404
+                let is_html = content_type == "text/html";
+                let content_type = if content_type.starts_with("image/") {
+                    content_type
+                } else {
+                    "image/x-icon".to_owned()
+                };
+                match Limited::new(incoming, MAX_FAVICON_SIZE).collect().await {
+                    Ok(body) => match proxy::decompress(&proxy::get_content_encoding(&parts.headers), &body.to_bytes())
+                    {
+                        // fever clients prepend the data: scheme themselves
+                        Ok(icon) if !icon.is_empty() && icon.len() <= MAX_FAVICON_SIZE && !is_html => {
+                            format!("{};base64,{}", content_type, BASE64_STANDARD.encode(icon))
+                        }
+                        _ => String::new(),
+                    },
+                    Err(_) => String::new(),
+                }
+            }
+            Ok(fetched) => {
+                println!(
+                    "received status code {} fetching favicon {icon_url}",
+                    fetched.response.status()
+                );
+                String::new()
+            }
+            Err(e) => {
+                println!("!! error fetching favicon {icon_url}: {e:?}");
+                String::new()
+            }
+        };
+        println!("refreshing favicon {icon_url} [{feed_id}] ({} bytes)", data.len());
+        if let Err(e) = storage::transaction(db, |tx| {
+            Ok(storage::favicons::upsert_favicon(tx, feed_id, icon_url, &data)?)
+        }) {
+            println!("!! error saving favicon {icon_url}: {e:?}");
         }
     }
 
@@ -150,21 +451,26 @@ impl Pipe {
             None => p.url.to_owned(),
         };
         if p.status_code == StatusCode::NOT_MODIFIED {
-            if storage::transaction(&self.db, |tx| {
-                let feed_id = storage::feeds::get_feed_id_by_url(tx, &full_url);
+            let missing = storage::transaction(&self.db, |tx| {
+                let feed_id = storage::feeds::get_feed_id_by_url(tx, &full_url)?;
                 if feed_id.is_some() {
-                    storage::feeds::upsert_feed(tx, &full_url, None);
-                    false
+                    storage::feeds::upsert_feed(tx, &full_url, None)?;
+                    Ok(false)
                 } else {
-                    true
+                    Ok(true)
                 }
-            }) {
+            });
+            if let Err(e) = &missing {
+                metrics::pipe_error();
+                println!("!! error refreshing feed {full_url}: {e:?}");
+            }
+            if missing.is_ok_and(|v| v) {
                 println!("received status code 304 without existing feed, fetching again without cache: {full_url}");
-                if let Ok(response) = proxy::http_https_get(&full_url, &self.proxy).await {
-                    if let Err(e) = self.enqueue_response_body(&p.url, &p.query, response).await {
-                        metrics::pipe_error();
-                        println!("!! error enqueuing response body: {e:?}");
-                    }
+                if let Ok(fetched) = proxy::http_https_get(&full_url, &self.proxy, self.redirects).await
+                    && let Err(e) = self.enqueue_response_body(&p.url, &p.query, None, fetched).await
+                {
+                    metrics::pipe_error();
+                    println!("!! error enqueuing response body: {e:?}");
                 }
             }
         } else {
@@ -176,12 +482,33 @@ impl Pipe {
         }
     }
 
+    async fn fetch_scheduled(&self, full_url: &str) {
+        let (url, query) = match full_url.split_once('?') {
+            Some((u, q)) => (u, Some(q.to_owned())),
+            None => (full_url, None),
+        };
+        match proxy::http_https_get(full_url, &self.proxy, self.redirects).await {
+            Ok(fetched) => {
+                if let Err(e) = self.enqueue_response_body(url, &query, None, fetched).await {
+                    metrics::pipe_error();
+                    println!("!! error enqueuing response body: {e:?}");
+                }
+            }
+            Err(e) => {
+                metrics::pipe_error();
+                println!("!! error fetching scheduled feed {full_url}: {e:?}");
+            }
+        }
+    }
+
     async fn enqueue_response_body(
         &self,
         url: &str,
         query: &Option<String>,
-        response_in: Response<Incoming>,
+        accept_encoding: Option<&str>,
+        fetched: proxy::Fetched,
     ) -> Result<Response<Full<Bytes>>, common::PipeError> {
+        let response_in = fetched.response;
         let status_code = response_in.status();
         let return_empty_not_modified = match status_code {
             StatusCode::OK => {
@@ -218,19 +545,45 @@ impl Pipe {
             }
             Ok(empty_not_modified.body(Full::from(""))?)
         } else {
-            let (parts, incoming) = response_in.into_parts();
+            let (mut parts, incoming) = response_in.into_parts();
             let content = incoming.collect().await?.to_bytes();
+            let encoding = proxy::get_content_encoding(&parts.headers);
+            let decoded = match proxy::decompress(&encoding, &content) {
+                Ok(v) => Some(v),
+                Err(e) => {
+                    metrics::pipe_error();
+                    println!("!! error decompressing {encoding} body: {e:?}");
+                    None
+                }
+            };
             let parse_request = ParseRequest {
                 status_code,
+                headers: parts
+                    .headers
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), String::from_utf8_lossy(v.as_bytes()).into_owned()))
+                    .collect(),
                 url: url.to_owned(),
                 query: query.to_owned(),
-                body: content.to_owned(),
+                moved_to: fetched.moved_to,
+                body: decoded.to_owned().unwrap_or_else(|| content.to_owned()),
             };
             if let Err(e) = self.sender.send(parse_request).await {
                 metrics::pipe_error();
                 println!("!! error sending data to pipe: {e}");
             };
-            Ok(Response::from_parts(parts, Full::new(content)))
+            match decoded {
+                Some(body) if !encoding.is_empty() && !proxy::accepts_encoding(accept_encoding, &encoding) => {
+                    parts.headers.remove(header::CONTENT_ENCODING);
+                    parts.headers.remove(header::CONTENT_LENGTH);
+                    Ok(Response::from_parts(parts, Full::new(body)))
+                }
+                // the client can not decode the body either
+                None if !proxy::accepts_encoding(accept_encoding, &encoding) => {
+                    proxy::handle_error(handle_error(url, format!("unable to decompress {encoding} body")))
+                }
+                _ => Ok(Response::from_parts(parts, Full::new(content))),
+            }
         }
     }
 
@@ -240,8 +593,12 @@ impl Pipe {
         req: Request<Incoming>,
     ) -> Result<Response<Full<Bytes>>, common::PipeError> {
         let query: Option<String> = req.uri().query().map(|x| x.to_owned());
-        match proxy::http_call(uri, req).await {
-            Ok(response) => self.enqueue_response_body(uri, &query, response).await,
+        let accept_encoding = get_accept_encoding(&req);
+        match proxy::call(uri, req, &self.proxy, self.redirects).await {
+            Ok(fetched) => {
+                self.enqueue_response_body(uri, &query, accept_encoding.as_deref(), fetched)
+                    .await
+            }
             Err(error) => proxy::handle_error(handle_error(uri, format!("{error:?}"))),
         }
     }
@@ -252,8 +609,12 @@ impl Pipe {
         req: Request<Incoming>,
     ) -> Result<Response<Full<Bytes>>, common::PipeError> {
         let query: Option<String> = req.uri().query().map(|x| x.to_owned());
-        match proxy::https_call(uri, req, &self.proxy).await {
-            Ok(response) => self.enqueue_response_body(uri, &query, response).await,
+        let accept_encoding = get_accept_encoding(&req);
+        match proxy::call(uri, req, &self.proxy, self.redirects).await {
+            Ok(fetched) => {
+                self.enqueue_response_body(uri, &query, accept_encoding.as_deref(), fetched)
+                    .await
+            }
             Err(error) => proxy::handle_error(handle_error(uri, format!("{error:?}"))),
         }
     }
@@ -268,9 +629,32 @@ impl Pipe {
             _ => String::new(),
         };
         let (method, params) = path.split_once('/').unwrap_or((path, &body));
-        let content = self.methods.evaluate("invoke", method, params, false).unwrap_or(body);
+        let function = format!("invoke_{method}");
+        if !self.methods.has_function(&function) {
+            return self.send_invoke_content(path, body).await;
+        }
+        let (name, args) = (method.to_owned(), (params.to_owned(),));
+        let content = match self
+            .methods
+            .call(&function, move |script| script.evaluate("invoke", &name, args, false))
+            .await
+        {
+            Ok(v) => v.unwrap_or(body),
+            Err(common::PipeError::ScriptTimeoutError) => return common::gateway_timeout(),
+            Err(e) => return Err(e),
+        };
+        self.send_invoke_content(path, content).await
+    }
+
+    async fn send_invoke_content(
+        &self,
+        path: &str,
+        content: String,
+    ) -> Result<Response<Full<Bytes>>, common::PipeError> {
         let parse_request = ParseRequest {
+            headers: HashMap::new(),
             query: None,
+            moved_to: None,
             status_code: StatusCode::OK,
             body: Bytes::from(content.to_owned()),
             url: format!("rss-pipe://{}/{}", self.methods.get_name(), path),
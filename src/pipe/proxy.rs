@@ -1,8 +1,10 @@
+use std::io::Read;
 use std::str::FromStr;
 
-use bytes::Bytes;
+use bytes::{Buf, Bytes};
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
 use http::{
-    StatusCode,
+    HeaderMap, StatusCode, header,
     uri::{InvalidUri, Scheme, Uri},
 };
 use http_body_util::{Empty, Full};
@@ -10,14 +12,31 @@ use hyper::{Request, Response, body::Incoming};
 use hyper_socks2::SocksConnector;
 use hyper_tls::HttpsConnector;
 use hyper_util::client::legacy::{Client, connect::HttpConnector};
+use url::Url;
 
 use crate::common::PipeError;
 
+const ACCEPT_ENCODING: &str = "gzip, deflate, br";
+// compressed bodies beyond this size are rejected instead of exhausting memory
+const MAX_DECOMPRESSED_SIZE: u64 = 64 * 1024 * 1024;
+// headers still sent after a redirect to another origin, anything else may carry credentials
+const CROSS_ORIGIN_HEADERS: [header::HeaderName; 4] = [
+    header::ACCEPT,
+    header::ACCEPT_ENCODING,
+    header::ACCEPT_LANGUAGE,
+    header::USER_AGENT,
+];
+
 enum FetchRequest {
-    GetUri(Uri),
+    GetUri(Uri, HeaderMap),
     RequestIncoming(Request<Incoming>),
 }
 
+pub struct Fetched {
+    pub response: Response<Incoming>,
+    pub moved_to: Option<String>,
+}
+
 fn forward_uri<B>(forward_url: &str, req: &Request<B>) -> Result<Uri, InvalidUri> {
     let forward_uri = match req.uri().query() {
         Some(query) => &format!("{forward_url}?{query}"),
@@ -28,13 +47,13 @@ fn forward_uri<B>(forward_url: &str, req: &Request<B>) -> Result<Uri, InvalidUri
 }
 
 fn create_proxied_request<B>(forward_url: &str, mut request: Request<B>) -> Result<Request<B>, PipeError> {
-    // todo: support gzip decompression later
+    // body is decompressed before parsing, see `decompress`
     match request.headers_mut().entry("Accept-Encoding") {
         hyper::header::Entry::Vacant(entry) => {
-            entry.insert("identity".parse()?);
+            entry.insert(ACCEPT_ENCODING.parse()?);
         }
         hyper::header::Entry::Occupied(mut entry) => {
-            entry.insert("identity".parse()?);
+            entry.insert(ACCEPT_ENCODING.parse()?);
         }
     }
     // replace host
@@ -53,6 +72,12 @@ fn create_proxied_request<B>(forward_url: &str, mut request: Request<B>) -> Resu
     Ok(request)
 }
 
+fn create_get_request(uri: Uri, headers: HeaderMap) -> Result<Request<Empty<Bytes>>, PipeError> {
+    let mut request = Request::builder().uri(uri).body(Empty::<Bytes>::new())?;
+    *request.headers_mut() = headers;
+    Ok(request)
+}
+
 async fn handle_response(
     response: Result<Response<Incoming>, hyper_util::client::legacy::Error>,
 ) -> Result<Response<Incoming>, PipeError> {
@@ -62,6 +87,21 @@ async fn handle_response(
     }
 }
 
+async fn http_fetch(request: FetchRequest) -> Result<Response<Incoming>, PipeError> {
+    let builder = Client::builder(hyper_util::rt::TokioExecutor::new());
+    let response = match request {
+        FetchRequest::RequestIncoming(request) => {
+            let client = builder.build(HttpConnector::new());
+            client.request(request).await
+        }
+        FetchRequest::GetUri(uri, headers) => {
+            let client = builder.build(HttpConnector::new());
+            client.request(create_get_request(uri, headers)?).await
+        }
+    };
+    handle_response(response).await
+}
+
 async fn https_fetch(request: FetchRequest, proxy: &str) -> Result<Response<Incoming>, PipeError> {
     let builder = Client::builder(hyper_util::rt::TokioExecutor::new());
     let response = if proxy.is_empty() {
@@ -70,10 +110,9 @@ async fn https_fetch(request: FetchRequest, proxy: &str) -> Result<Response<Inco
                 let client = builder.build(HttpsConnector::new());
                 client.request(request).await
             }
-            FetchRequest::GetUri(uri) => {
+            FetchRequest::GetUri(uri, headers) => {
                 let client = builder.build(HttpsConnector::new());
-                let request = Request::builder().uri(uri).body(Empty::<Bytes>::new())?;
-                client.request(request).await
+                client.request(create_get_request(uri, headers)?).await
             }
         }
     } else {
@@ -90,47 +129,155 @@ async fn https_fetch(request: FetchRequest, proxy: &str) -> Result<Response<Inco
                 let client = builder.build(proxy);
                 client.request(request).await
             }
-            FetchRequest::GetUri(uri) => {
+            FetchRequest::GetUri(uri, headers) => {
                 let client = builder.build(proxy);
-                let request = Request::builder().uri(uri).body(Empty::<Bytes>::new())?;
-                client.request(request).await
+                client.request(create_get_request(uri, headers)?).await
             }
         }
     };
     handle_response(response).await
 }
 
-pub async fn http_https_get(uri: &str, proxy: &str) -> Result<Response<Incoming>, PipeError> {
-    let uri_parsed = Uri::from_str(uri)?;
-    if let Some(a) = uri_parsed.scheme() {
-        if a == &Scheme::HTTPS {
-            return https_fetch(FetchRequest::GetUri(uri_parsed), proxy).await;
-        } else if a == &Scheme::HTTP {
-            let builder = Client::builder(hyper_util::rt::TokioExecutor::new());
-            let request = Request::builder().uri(uri).body(Empty::<Bytes>::new())?;
-            let response = builder.build(HttpConnector::new()).request(request).await;
-            return handle_response(response).await;
-        };
+async fn fetch(request: FetchRequest, proxy: &str) -> Result<Response<Incoming>, PipeError> {
+    let scheme = match &request {
+        FetchRequest::GetUri(uri, _) => uri.scheme(),
+        FetchRequest::RequestIncoming(request) => request.uri().scheme(),
     };
-    Err(PipeError::UnsupportedSchemeError)
+    match scheme {
+        Some(a) if a == &Scheme::HTTPS => https_fetch(request, proxy).await,
+        Some(a) if a == &Scheme::HTTP => http_fetch(request).await,
+        _ => Err(PipeError::UnsupportedSchemeError),
+    }
 }
 
-pub async fn https_call(
+fn same_origin(a: &Url, b: &Url) -> bool {
+    a.scheme() == b.scheme() && a.host_str() == b.host_str() && a.port_or_known_default() == b.port_or_known_default()
+}
+
+async fn follow_redirects(
+    uri: Uri,
+    mut headers: HeaderMap,
+    response_in: Response<Incoming>,
+    proxy: &str,
+    redirects: u8,
+) -> Result<Fetched, PipeError> {
+    let mut response = response_in;
+    let mut current = uri.to_string();
+    let mut moved_to = None;
+    let mut permanent = true;
+    let mut hops = 0;
+    while let StatusCode::MOVED_PERMANENTLY
+    | StatusCode::FOUND
+    | StatusCode::SEE_OTHER
+    | StatusCode::TEMPORARY_REDIRECT
+    | StatusCode::PERMANENT_REDIRECT = response.status()
+    {
+        let location = match response.headers().get(header::LOCATION).and_then(|v| v.to_str().ok()) {
+            Some(v) => v,
+            None => break,
+        };
+        if hops >= redirects {
+            if redirects == 0 {
+                break; // following redirects is disabled, return the response verbatim
+            }
+            return Err(PipeError::TooManyRedirectsError);
+        }
+        let current_url = Url::parse(&current)?;
+        let next_url = current_url.join(location)?;
+        if !same_origin(&current_url, &next_url) {
+            headers = headers
+                .iter()
+                .filter(|(name, _)| CROSS_ORIGIN_HEADERS.contains(name))
+                .map(|(name, value)| (name.to_owned(), value.to_owned()))
+                .collect();
+        }
+        let next = next_url.to_string();
+        // only a chain of permanent redirects moves the feed, a temporary hop ends it
+        permanent = permanent
+            && matches!(
+                response.status(),
+                StatusCode::MOVED_PERMANENTLY | StatusCode::PERMANENT_REDIRECT
+            );
+        if permanent {
+            moved_to = Some(next.to_owned());
+        }
+        println!("following redirect {} {current} -> {next}", response.status().as_u16());
+        let next_uri = Uri::from_str(&next)?;
+        let mut next_headers = headers.clone();
+        next_headers.remove(header::HOST);
+        response = fetch(FetchRequest::GetUri(next_uri, next_headers), proxy).await?;
+        current = next;
+        hops += 1;
+    }
+    Ok(Fetched { response, moved_to })
+}
+
+pub async fn http_https_get(uri: &str, proxy: &str, redirects: u8) -> Result<Fetched, PipeError> {
+    let uri_parsed = Uri::from_str(uri)?;
+    let mut headers = HeaderMap::new();
+    headers.insert(header::ACCEPT_ENCODING, ACCEPT_ENCODING.parse()?);
+    let response = fetch(FetchRequest::GetUri(uri_parsed.to_owned(), headers.to_owned()), proxy).await?;
+    follow_redirects(uri_parsed, headers, response, proxy, redirects).await
+}
+
+pub async fn call(
     forward_uri: &str,
     request: Request<Incoming>,
     proxy: &str,
-) -> Result<Response<Incoming>, PipeError> {
+    redirects: u8,
+) -> Result<Fetched, PipeError> {
     let proxied_request = create_proxied_request(forward_uri, request)?;
-    https_fetch(FetchRequest::RequestIncoming(proxied_request), proxy).await
+    let uri = proxied_request.uri().to_owned();
+    let headers = proxied_request.headers().to_owned();
+    let response = fetch(FetchRequest::RequestIncoming(proxied_request), proxy).await?;
+    follow_redirects(uri, headers, response, proxy, redirects).await
 }
 
-pub async fn http_call(forward_uri: &str, request: Request<Incoming>) -> Result<Response<Incoming>, PipeError> {
-    let proxied_request = create_proxied_request(forward_uri, request)?;
-    let response = Client::builder(hyper_util::rt::TokioExecutor::new())
-        .build(HttpConnector::new())
-        .request(proxied_request)
-        .await;
-    handle_response(response).await
+pub fn get_content_encoding(headers: &HeaderMap) -> String {
+    headers
+        .get(header::CONTENT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .trim()
+        .to_lowercase()
+}
+
+fn read_limited(decoder: impl Read, decoded: &mut Vec<u8>) -> Result<usize, PipeError> {
+    let size = decoder.take(MAX_DECOMPRESSED_SIZE + 1).read_to_end(decoded)?;
+    if size as u64 > MAX_DECOMPRESSED_SIZE {
+        return Err(PipeError::DecompressedTooLargeError);
+    }
+    Ok(size)
+}
+
+pub fn decompress(encoding: &str, body: &Bytes) -> Result<Bytes, PipeError> {
+    let mut decoded = Vec::new();
+    match encoding {
+        "gzip" | "x-gzip" => read_limited(GzDecoder::new(body.clone().reader()), &mut decoded)?,
+        // "deflate" should be zlib wrapped, but some servers send raw deflate streams
+        "deflate" => match read_limited(ZlibDecoder::new(body.clone().reader()), &mut decoded) {
+            Ok(v) => v,
+            Err(PipeError::IoError(_)) => {
+                decoded.clear();
+                read_limited(DeflateDecoder::new(body.clone().reader()), &mut decoded)?
+            }
+            Err(e) => return Err(e),
+        },
+        "br" => read_limited(
+            brotli_decompressor::Decompressor::new(body.clone().reader(), 4096),
+            &mut decoded,
+        )?,
+        _ => return Ok(body.clone()),
+    };
+    Ok(Bytes::from(decoded))
+}
+
+pub fn accepts_encoding(accept_encoding: Option<&str>, encoding: &str) -> bool {
+    accept_encoding.unwrap_or("").split(',').any(|v| {
+        let (name, quality) = v.split_once(';').unwrap_or((v, ""));
+        let name = name.trim();
+        (name == encoding || name == "*") && quality.trim().trim_start_matches("q=").parse::<f32>().unwrap_or(1.0) > 0.0
+    })
 }
 
 pub fn handle_error(error: String) -> Result<Response<Full<Bytes>>, PipeError> {
@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::common::PipeError;
+use crate::{metrics, storage};
+
+const TICK: Duration = Duration::from_secs(3600);
+
+#[derive(Clone, Copy)]
+pub struct Retention {
+    max_age: u64,
+    max_items: u64,
+}
+
+impl Retention {
+    pub fn new(max_age: u64, max_items: u64) -> Self {
+        Self { max_age, max_items }
+    }
+
+    fn prune(&self, db: &str) -> Result<usize, PipeError> {
+        storage::transaction(db, |tx| {
+            // per-feed rules fall back to the global limits, saved and commented items are kept by default
+            let rules: HashMap<u64, storage::retention::RetentionRule> =
+                storage::retention::get_all_retention_rules(tx)?
+                    .into_iter()
+                    .map(|rule| (rule.feed_id, rule))
+                    .collect();
+            let mut total = 0;
+            for (feed, _) in storage::feeds::get_all_feeds(tx)? {
+                let rule = rules.get(&feed.id);
+                let pruned = storage::retention::prune_feed_items(
+                    tx,
+                    feed.id,
+                    rule.and_then(|v| v.max_age).unwrap_or(self.max_age),
+                    rule.and_then(|v| v.max_items).unwrap_or(self.max_items),
+                    rule.is_none_or(|v| v.keep_saved),
+                    rule.is_none_or(|v| v.keep_commented),
+                )?;
+                if pruned > 0 {
+                    println!("pruned {pruned} items of feed {} [{}]", feed.title, feed.id);
+                }
+                total += pruned;
+            }
+            Ok(total)
+        })
+    }
+
+    pub async fn run(self, db: String) {
+        loop {
+            tokio::time::sleep(TICK).await;
+            // pruning and vacuuming may take a while, keep them off the async workers
+            let (retention, db) = (self, db.clone());
+            let result = tokio::task::spawn_blocking(move || {
+                match retention.prune(&db) {
+                    Ok(pruned) => metrics::items_pruned(pruned as u64),
+                    Err(e) => println!("!! error pruning items: {e:?}"),
+                }
+                if let Err(e) = storage::vacuum(&db) {
+                    println!("!! error vacuuming database: {e:?}");
+                }
+            })
+            .await;
+            if let Err(e) = result {
+                println!("!! error running retention: {e:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::sync::Semaphore;
+
+use crate::{pipe::Pipe, storage};
+
+const TICK: Duration = Duration::from_secs(30);
+
+pub struct Scheduler {
+    interval: u64,
+    jitter: u64,
+    concurrency: usize,
+    intervals: HashMap<String, u64>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::from_secs(0))
+        .as_secs()
+}
+
+impl Scheduler {
+    pub fn new(interval: &str, jitter: &str, concurrency: &str, intervals: Option<HashMap<String, u64>>) -> Self {
+        Self {
+            interval: interval.parse().unwrap_or(0),
+            jitter: jitter.parse().unwrap_or(0),
+            concurrency: concurrency.parse().unwrap_or(1).max(1),
+            intervals: intervals.unwrap_or_default(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.interval > 0 || self.intervals.values().any(|v| *v > 0)
+    }
+
+    fn get_interval(&self, url: &str) -> u64 {
+        // per-feed intervals from `schedule` in the pipe script take precedence, 0 disables polling
+        self.intervals.get(url).copied().unwrap_or(self.interval)
+    }
+
+    fn get_jitter(&self) -> u64 {
+        if self.jitter > 0 {
+            fastrand::u64(0..self.jitter)
+        } else {
+            0
+        }
+    }
+
+    pub async fn run(self, pipe: Arc<Pipe>) {
+        let semaphore = Arc::new(Semaphore::new(self.concurrency));
+        let mut next_fetch: HashMap<u64, u64> = HashMap::new();
+        loop {
+            let feeds = storage::read_transaction(&pipe.db, |tx| Ok(storage::feeds::get_all_feeds(tx)?))
+                .unwrap_or_else(|e| {
+                    println!("!! error loading feeds to schedule: {e:?}");
+                    vec![]
+                });
+            for (feed, feed_url) in feeds {
+                let interval = self.get_interval(&feed_url.url);
+                if interval == 0 || !(feed_url.url.starts_with("http://") || feed_url.url.starts_with("https://")) {
+                    continue;
+                }
+                let current = now();
+                let due = *next_fetch
+                    .entry(feed.id)
+                    .or_insert_with(|| feed.last_updated + interval + self.get_jitter());
+                if due > current {
+                    continue;
+                }
+                next_fetch.insert(feed.id, current + interval + self.get_jitter());
+                let permit = match semaphore.clone().acquire_owned().await {
+                    Ok(p) => p,
+                    Err(_) => return,
+                };
+                let fetcher = pipe.clone();
+                tokio::spawn(async move {
+                    fetcher.fetch_scheduled(&feed_url.url).await;
+                    drop(permit);
+                });
+            }
+            tokio::time::sleep(TICK).await;
+        }
+    }
+}
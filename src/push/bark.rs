@@ -1,74 +1,52 @@
 use bytes::Bytes;
 use http_body_util::Full;
 use hyper::{Method, Request};
-use hyper_util::client::legacy::{Client, connect::HttpConnector};
 use serde::Serialize;
 
-use crate::common::extract_content;
+use crate::common::PipeError;
+use crate::push::{Backend, Notification};
 
 #[derive(Debug, Serialize)]
-struct BarkRequest {
-    body: String,
-    title: String,
-    group: String,
+struct BarkRequest<'a> {
+    body: &'a str,
+    title: &'a str,
+    group: &'a str,
     #[serde(skip_serializing_if = "Option::is_none")]
-    url: Option<String>,
+    url: Option<&'a str>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    image: Option<String>,
+    image: Option<&'a str>,
 }
 
-impl BarkRequest {
-    pub async fn send_notification(&self, destination: &str) {
-        let builder = Client::builder(hyper_util::rt::TokioExecutor::new());
-        let client = builder.build(HttpConnector::new());
-        let body = serde_json::to_string(self).unwrap_or("null".to_owned());
-        println!("building bark push request: {body}");
-        let req = Request::builder()
-            .uri(destination)
-            .method(Method::POST)
-            .header("Content-Type", "application/json")
-            .body(Full::new(Bytes::from(body)));
-        match req {
-            Ok(v) => match client.request(v).await {
-                Ok(s) => {
-                    println!("complete bark push {}", s.status());
-                }
-                Err(e) => {
-                    println!("!! error received from bark push: {e}");
-                }
-            },
-            Err(_) => {
-                println!(
-                    "======== Bark Preview ========\n{}\n{}\n==============================",
-                    self.title,
-                    self.body.trim_end_matches("\n")
-                )
-            }
+pub struct Bark {
+    destination: String,
+}
+
+impl Bark {
+    pub fn new(destination: &str) -> Self {
+        Self {
+            destination: destination.to_owned(),
         }
     }
 }
 
-pub async fn send_notification(
-    feed_title: &str,
-    item_title: &str,
-    content: &str,
-    group: &str,
-    link: Option<String>,
-    image: Option<String>,
-    destination: &str,
-) {
-    let extracted_content = extract_content::extract_content(item_title, content, 250);
-    BarkRequest {
-        title: feed_title.to_owned(),
-        group: if group.is_empty() {
-            "rss_pipe_rust".to_owned()
-        } else {
-            group.to_owned()
-        },
-        body: extracted_content,
-        url: link,
-        image,
+impl Backend for Bark {
+    fn name(&self) -> &str {
+        "bark"
+    }
+
+    fn build_request(&self, notification: &Notification) -> Result<Request<Full<Bytes>>, PipeError> {
+        let body = serde_json::to_string(&BarkRequest {
+            body: &notification.body,
+            title: &notification.title,
+            group: &notification.group,
+            url: notification.url.as_deref(),
+            image: notification.image.as_deref(),
+        })?;
+        println!("building bark push request: {body}");
+        Ok(Request::builder()
+            .uri(&self.destination)
+            .method(Method::POST)
+            .header("Content-Type", "application/json")
+            .body(Full::new(Bytes::from(body)))?)
     }
-    .send_notification(destination)
-    .await;
 }
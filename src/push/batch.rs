@@ -0,0 +1,27 @@
+use crate::common::extract_content;
+use crate::push::Notification;
+
+const TOP_TITLES: usize = 5;
+
+pub fn summarize(mut notifications: Vec<Notification>) -> Option<Notification> {
+    if notifications.len() <= 1 {
+        return notifications.pop();
+    }
+    let count = notifications.len();
+    let mut titles: Vec<String> = notifications
+        .iter()
+        .take(TOP_TITLES)
+        .map(|v| format!("· {}", v.item_title))
+        .collect();
+    if count > TOP_TITLES {
+        titles.push(format!("and {} more", count - TOP_TITLES));
+    }
+    let first = notifications.swap_remove(0);
+    Some(Notification {
+        body: extract_content::extract_content(&format!("{count} new items"), &titles.join("\n"), 250),
+        item_title: String::new(),
+        url: None,
+        image: None,
+        ..first
+    })
+}
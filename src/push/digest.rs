@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{Local, NaiveTime};
+
+use crate::common::extract_content;
+use crate::push::{Notification, Push};
+use crate::storage;
+
+const TOP_ITEMS: u64 = 10;
+
+fn until_next(at: NaiveTime) -> Duration {
+    let now = Local::now().naive_local();
+    let today = now.date().and_time(at);
+    let next = if today > now {
+        today
+    } else {
+        today + chrono::Duration::days(1)
+    };
+    (next - now).to_std().unwrap_or(Duration::from_secs(60))
+}
+
+fn build_digest(db: &str) -> Option<Notification> {
+    let (total, items, feeds) = storage::read_transaction(db, |tx| {
+        let total: u64 = storage::items::get_unread_counts(tx)?
+            .iter()
+            .map(|(_, count, _)| count)
+            .sum();
+        let filter = storage::items::ItemFilter {
+            is_read: Some(0),
+            limit: TOP_ITEMS,
+            ..Default::default()
+        };
+        let items = storage::items::get_filtered_items(tx, &filter)?;
+        let feeds: HashMap<u64, String> = storage::feeds::get_all_feeds(tx)?
+            .into_iter()
+            .map(|(feed, _)| (feed.id, feed.title))
+            .collect();
+        Ok((total, items, feeds))
+    })
+    .inspect_err(|e| println!("!! error loading digest: {e:?}"))
+    .ok()?;
+    if total == 0 {
+        return None;
+    }
+    let mut titles: Vec<String> = items
+        .iter()
+        .map(|item| format!("· {}: {}", feeds.get(&item.feed_id).map_or("", |v| v), item.title))
+        .collect();
+    if total > items.len() as u64 {
+        titles.push(format!("and {} more", total - items.len() as u64));
+    }
+    Some(Notification {
+        title: "Daily Digest".to_owned(),
+        item_title: String::new(),
+        body: extract_content::extract_content(&format!("{total} unread items"), &titles.join("\n"), 500),
+        group: "rss_pipe_digest".to_owned(),
+        url: None,
+        image: None,
+    })
+}
+
+pub async fn run(push: Arc<Push>, db: String, at: NaiveTime) {
+    loop {
+        tokio::time::sleep(until_next(at)).await;
+        match build_digest(&db) {
+            Some(notification) => push.send_notification(notification).await,
+            None => println!("skipping daily digest without unread items"),
+        }
+    }
+}
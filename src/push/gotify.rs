@@ -0,0 +1,44 @@
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::{Method, Request};
+use serde_json::json;
+
+use crate::common::PipeError;
+use crate::push::{Backend, Notification};
+
+pub struct Gotify {
+    destination: String,
+}
+
+impl Gotify {
+    /// `destination` is the message endpoint with an application token, e.g. `https://gotify.example.com/message?token=...`
+    pub fn new(destination: &str) -> Self {
+        Self {
+            destination: destination.to_owned(),
+        }
+    }
+}
+
+impl Backend for Gotify {
+    fn name(&self) -> &str {
+        "gotify"
+    }
+
+    fn build_request(&self, notification: &Notification) -> Result<Request<Full<Bytes>>, PipeError> {
+        let mut body = json!({
+            "title": notification.title,
+            "message": notification.body,
+            "priority": 5,
+        });
+        if let Some(url) = &notification.url {
+            body["extras"] = json!({"client::notification": {"click": {"url": url}}});
+        }
+        let body = serde_json::to_string(&body)?;
+        println!("building gotify push request: {body}");
+        Ok(Request::builder()
+            .uri(&self.destination)
+            .method(Method::POST)
+            .header("Content-Type", "application/json")
+            .body(Full::new(Bytes::from(body)))?)
+    }
+}
@@ -1,3 +1,187 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::Bytes;
+use http::Request;
+use http_body_util::Full;
+use hyper_tls::HttpsConnector;
+use hyper_util::client::legacy::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::common::{PipeError, extract_content};
+
 pub mod bark;
+pub mod batch;
+pub mod digest;
+pub mod gotify;
+pub mod ntfy;
+pub mod outbox;
+pub mod rules;
+pub mod webhook;
+
+// a slow backend fails the push, which is then retried from the outbox
+const PUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub title: String,
+    pub item_title: String,
+    pub body: String,
+    pub group: String,
+    pub url: Option<String>,
+    pub image: Option<String>,
+}
+
+impl Notification {
+    pub fn new(
+        title: &str,
+        item_title: &str,
+        content: &str,
+        group: &str,
+        url: Option<String>,
+        image: Option<String>,
+    ) -> Self {
+        Self {
+            title: title.to_owned(),
+            item_title: item_title.to_owned(),
+            body: extract_content::extract_content(item_title, content, 250),
+            group: if group.is_empty() {
+                "rss_pipe_rust".to_owned()
+            } else {
+                group.to_owned()
+            },
+            url: url.filter(|v| !v.is_empty()),
+            image,
+        }
+    }
+}
+
+pub trait Backend: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn build_request(&self, notification: &Notification) -> Result<Request<Full<Bytes>>, PipeError>;
+}
+
+pub struct Push {
+    db: String,
+    // keyed by the configured `kind+url`, which is also saved in the outbox for retries
+    backends: Vec<(String, Box<dyn Backend>)>,
+    batch: u64,
+    pending: Mutex<HashMap<u64, Vec<Notification>>>,
+}
+
+fn create_backend(kind: &str, destination: &str) -> Option<Box<dyn Backend>> {
+    match kind {
+        "bark" => Some(Box::new(bark::Bark::new(destination))),
+        "gotify" => Some(Box::new(gotify::Gotify::new(destination))),
+        "ntfy" => ntfy::Ntfy::new(destination).map(|v| Box::new(v) as Box<dyn Backend>),
+        "webhook" => Some(Box::new(webhook::Webhook::new(destination))),
+        _ => None,
+    }
+}
+
+async fn send_request(backend: &dyn Backend, notification: &Notification) -> Result<(), PipeError> {
+    let client = Client::builder(hyper_util::rt::TokioExecutor::new()).build(HttpsConnector::new());
+    let response = tokio::time::timeout(PUSH_TIMEOUT, client.request(backend.build_request(notification)?))
+        .await
+        .map_err(|_| PipeError::PushTimeoutError)??;
+    if response.status().is_success() {
+        println!("complete {} push {}", backend.name(), response.status());
+        Ok(())
+    } else {
+        Err(PipeError::PushStatusError(response.status()))
+    }
+}
+
+impl Push {
+    /// `destinations` is a comma separated list of `kind+url`, `bark` is kept as a shorthand for `bark+url`
+    pub fn new(db: &str, destinations: &str, bark: &str, batch: &str) -> Self {
+        let mut backends = vec![];
+        let bark_destination = (!bark.is_empty()).then(|| format!("bark+{bark}"));
+        for destination in destinations.split(',').chain(bark_destination.as_deref()) {
+            let destination = destination.trim();
+            if destination.is_empty() {
+                continue;
+            }
+            match destination
+                .split_once('+')
+                .and_then(|(kind, url)| create_backend(kind, url))
+            {
+                Some(v) => backends.push((destination.to_owned(), v)),
+                None => println!("!! unsupported push destination: {destination}"),
+            }
+        }
+        Self {
+            db: db.to_owned(),
+            backends,
+            batch: batch.parse().unwrap_or(0),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// notifications of the same feed within `batch` seconds are sent as a single summary
+    pub async fn queue_notification(self: &Arc<Self>, feed_id: u64, notification: Notification) {
+        if self.batch == 0 {
+            // sent in the background so feeds are not held up by slow backends
+            let push = self.clone();
+            tokio::spawn(async move { push.send_notification(notification).await });
+            return;
+        }
+        let first = match self.pending.lock() {
+            Ok(mut pending) => {
+                let notifications = pending.entry(feed_id).or_default();
+                notifications.push(notification);
+                notifications.len() == 1
+            }
+            Err(e) => {
+                println!("!! error locking pending notifications: {e}");
+                return;
+            }
+        };
+        if first {
+            let push = self.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_secs(push.batch)).await;
+                push.flush(feed_id).await;
+            });
+        }
+    }
+
+    async fn flush(&self, feed_id: u64) {
+        let notifications = match self.pending.lock() {
+            Ok(mut pending) => pending.remove(&feed_id).unwrap_or_default(),
+            Err(e) => {
+                println!("!! error locking pending notifications: {e}");
+                return;
+            }
+        };
+        if let Some(notification) = batch::summarize(notifications) {
+            self.send_notification(notification).await;
+        }
+    }
+
+    pub async fn send_notification(&self, notification: Notification) {
+        if self.backends.is_empty() {
+            println!(
+                "======== Push Preview ========\n{}\n{}\n==============================",
+                notification.title,
+                notification.body.trim_end_matches("\n")
+            );
+            return;
+        }
+        for (destination, backend) in &self.backends {
+            if let Err(e) = send_request(backend.as_ref(), &notification).await {
+                println!("!! error sending {} push, saving to outbox: {e:?}", backend.name());
+                outbox::save(&self.db, destination, &notification, &format!("{e:?}"));
+            }
+        }
+    }
 
-// todo: implement custom push service support
+    fn get_backend(&self, destination: &str) -> Option<&dyn Backend> {
+        self.backends
+            .iter()
+            .find(|(v, _)| v == destination)
+            .map(|(_, backend)| backend.as_ref())
+    }
+}
@@ -0,0 +1,72 @@
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::{Method, Request};
+use serde::Serialize;
+use url::Url;
+
+use crate::common::PipeError;
+use crate::push::{Backend, Notification};
+
+// published as json to the server root, so titles do not need to fit in http headers
+#[derive(Debug, Serialize)]
+struct NtfyRequest<'a> {
+    topic: &'a str,
+    title: &'a str,
+    message: &'a str,
+    tags: [&'a str; 1],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    click: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attach: Option<&'a str>,
+}
+
+pub struct Ntfy {
+    server: String,
+    topic: String,
+    token: Option<String>,
+}
+
+impl Ntfy {
+    /// `destination` is the topic url, e.g. `https://ntfy.sh/my_topic`, with an optional `?auth=` access token
+    pub fn new(destination: &str) -> Option<Self> {
+        let mut url = Url::parse(destination).ok()?;
+        let topic = url.path_segments()?.next_back()?.to_owned();
+        let token = url
+            .query_pairs()
+            .find(|(k, _)| k == "auth")
+            .map(|(_, v)| v.into_owned());
+        url.set_query(None);
+        url.path_segments_mut().ok()?.pop();
+        Some(Self {
+            server: url.to_string(),
+            topic,
+            token,
+        })
+    }
+}
+
+impl Backend for Ntfy {
+    fn name(&self) -> &str {
+        "ntfy"
+    }
+
+    fn build_request(&self, notification: &Notification) -> Result<Request<Full<Bytes>>, PipeError> {
+        let body = serde_json::to_string(&NtfyRequest {
+            topic: &self.topic,
+            title: &notification.title,
+            message: &notification.body,
+            tags: [&notification.group],
+            click: notification.url.as_deref(),
+            attach: notification.image.as_deref(),
+        })?;
+        println!("building ntfy push request: {body}");
+        let mut builder = Request::builder()
+            .uri(&self.server)
+            .method(Method::POST)
+            .header("Content-Type", "application/json");
+        if let Some(token) = &self.token {
+            builder = builder.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(builder.body(Full::new(Bytes::from(body)))?)
+    }
+}
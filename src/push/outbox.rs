@@ -0,0 +1,82 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::push::{Notification, Push, send_request};
+use crate::storage;
+
+const TICK: Duration = Duration::from_secs(15);
+const RETRY_BASE: u64 = 30;
+const RETRY_MAX: u64 = 21600;
+const MAX_ATTEMPTS: u64 = 12;
+
+fn get_delay(attempts: u64) -> u64 {
+    // exponential backoff: 30s, 1m, 2m, 4m, ... up to 6h
+    RETRY_BASE
+        .saturating_mul(1 << attempts.saturating_sub(1).min(20))
+        .min(RETRY_MAX)
+}
+
+pub fn save(db: &str, destination: &str, notification: &Notification, error: &str) {
+    match serde_json::to_string(notification) {
+        Ok(v) => {
+            if let Err(e) = storage::transaction(db, |tx| {
+                Ok(storage::push_outbox::add_outbox_entry(
+                    tx,
+                    destination,
+                    &v,
+                    error,
+                    get_delay(1),
+                )?)
+            }) {
+                println!("!! error saving push to outbox: {e:?}");
+            }
+        }
+        Err(e) => println!("!! error serializing push for outbox: {e}"),
+    }
+}
+
+pub async fn run(push: Arc<Push>) {
+    loop {
+        tokio::time::sleep(TICK).await;
+        let entries =
+            match storage::read_transaction(&push.db, |tx| Ok(storage::push_outbox::get_due_outbox_entries(tx)?)) {
+                Ok(v) => v,
+                Err(e) => {
+                    println!("!! error loading push outbox: {e:?}");
+                    continue;
+                }
+            };
+        for entry in entries {
+            let notification: Option<Notification> = serde_json::from_str(&entry.notification).ok();
+            let result = match (push.get_backend(&entry.destination), notification) {
+                (Some(backend), Some(notification)) => {
+                    send_request(backend, &notification).await.map_err(|e| format!("{e:?}"))
+                }
+                (None, _) => Err("push destination is no longer configured".to_owned()),
+                (_, None) => Err("invalid notification".to_owned()),
+            };
+            let saved = storage::transaction(&push.db, |tx| match &result {
+                Ok(_) => {
+                    println!("delivered push {} from outbox to {}", entry.id, entry.destination);
+                    Ok(storage::push_outbox::delete_outbox_entry(tx, entry.id)?)
+                }
+                Err(e) if entry.attempts + 1 >= MAX_ATTEMPTS || push.get_backend(&entry.destination).is_none() => {
+                    println!("!! giving up push {} to {}: {e}", entry.id, entry.destination);
+                    Ok(storage::push_outbox::fail_outbox_entry(tx, entry.id, e)?)
+                }
+                Err(e) => {
+                    println!("!! error retrying push {} to {}: {e}", entry.id, entry.destination);
+                    Ok(storage::push_outbox::reschedule_outbox_entry(
+                        tx,
+                        entry.id,
+                        e,
+                        get_delay(entry.attempts + 1),
+                    )?)
+                }
+            });
+            if let Err(e) = saved {
+                println!("!! error updating push outbox entry {}: {e:?}", entry.id);
+            }
+        }
+    }
+}
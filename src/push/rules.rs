@@ -0,0 +1,46 @@
+use chrono::NaiveTime;
+
+use crate::storage::push_rules::PushRule;
+
+pub const MODES: [&str; 3] = ["always", "muted", "keyword"];
+
+pub fn parse_time(v: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(v, "%H:%M").ok()
+}
+
+fn is_quiet(rule: &PushRule, now: NaiveTime) -> bool {
+    match (parse_time(&rule.quiet_start), parse_time(&rule.quiet_end)) {
+        (Some(start), Some(end)) if start <= end => start <= now && now < end,
+        // the window wraps around midnight, e.g. 22:00 - 07:00
+        (Some(start), Some(end)) => now >= start || now < end,
+        _ => false,
+    }
+}
+
+fn matches_rule(rule: &PushRule, title: &str, content: &str, now: NaiveTime) -> bool {
+    if is_quiet(rule, now) {
+        return false;
+    }
+    match rule.mode.as_str() {
+        "muted" => false,
+        "keyword" => {
+            let title = title.to_lowercase();
+            let content = content.to_lowercase();
+            rule.keywords.iter().any(|keyword| {
+                let keyword = keyword.to_lowercase();
+                title.contains(&keyword) || content.contains(&keyword)
+            })
+        }
+        _ => true,
+    }
+}
+
+/// `rules` is the feed rule (if any) followed by rules of its groups; a feed rule overrides group rules, and
+/// an item is pushed when any of the group rules accepts it. Without rules everything is pushed.
+pub fn should_notify(rules: &[PushRule], title: &str, content: &str, now: NaiveTime) -> bool {
+    match rules.first() {
+        None => true,
+        Some(rule) if rule.target == "feed" => matches_rule(rule, title, content, now),
+        Some(_) => rules.iter().any(|rule| matches_rule(rule, title, content, now)),
+    }
+}
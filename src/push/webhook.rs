@@ -0,0 +1,50 @@
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::{Method, Request};
+use serde::Serialize;
+
+use crate::common::PipeError;
+use crate::push::{Backend, Notification};
+
+#[derive(Debug, Serialize)]
+struct WebhookRequest<'a> {
+    title: &'a str,
+    body: &'a str,
+    group: &'a str,
+    url: Option<&'a str>,
+    image: Option<&'a str>,
+}
+
+pub struct Webhook {
+    destination: String,
+}
+
+impl Webhook {
+    pub fn new(destination: &str) -> Self {
+        Self {
+            destination: destination.to_owned(),
+        }
+    }
+}
+
+impl Backend for Webhook {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    fn build_request(&self, notification: &Notification) -> Result<Request<Full<Bytes>>, PipeError> {
+        let body = serde_json::to_string(&WebhookRequest {
+            title: &notification.title,
+            body: &notification.body,
+            group: &notification.group,
+            url: notification.url.as_deref(),
+            image: notification.image.as_deref(),
+        })?;
+        println!("building webhook push request: {body}");
+        Ok(Request::builder()
+            .uri(&self.destination)
+            .method(Method::POST)
+            .header("Content-Type", "application/json")
+            .body(Full::new(Bytes::from(body)))?)
+    }
+}
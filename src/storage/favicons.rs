@@ -0,0 +1,37 @@
+use rusqlite::{OptionalExtension, Transaction};
+
+#[derive(Debug)]
+pub struct Favicon {
+    pub feed_id: u64,
+    pub data: String,
+}
+
+pub fn needs_refresh(tx: &Transaction, feed_id: u64, max_age: u64) -> Result<bool, rusqlite::Error> {
+    let expired: Option<bool> = tx
+        .query_row(
+            "select unixepoch() - unixepoch(last_updated) > ?1 from favicon where feed_id = ?2",
+            [max_age, feed_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(expired.unwrap_or(true))
+}
+
+pub fn upsert_favicon(tx: &Transaction, feed_id: u64, url: &str, data: &str) -> Result<usize, rusqlite::Error> {
+    tx.execute(
+        "insert into favicon (feed_id, url, data) values (?1, ?2, ?3) on conflict (feed_id) \
+        do update set url = excluded.url, data = excluded.data, last_updated = datetime()",
+        rusqlite::params![feed_id, url, data],
+    )
+}
+
+pub fn get_all_favicons(tx: &Transaction) -> Result<Vec<Favicon>, rusqlite::Error> {
+    tx.prepare("select feed_id, data from favicon where data != ''")?
+        .query_map([], |row| {
+            Ok(Favicon {
+                feed_id: row.get(0)?,
+                data: row.get(1)?,
+            })
+        })?
+        .collect()
+}
@@ -1,45 +1,51 @@
-use rusqlite::Transaction;
+use rusqlite::{OptionalExtension, Transaction};
+
+use crate::storage::groups;
 
 #[derive(Debug)]
 pub struct Feed {
     pub id: u64,
     pub title: String,
+    pub site_url: String,
     pub last_updated: u64,
 }
 
 #[derive(Debug)]
-#[allow(dead_code)]
 pub struct FeedUrl {
     pub id: u64,
     pub feed_id: u64,
     pub url: String,
 }
 
-pub fn get_last_refreshed_time(tx: &Transaction) -> u64 {
-    tx.query_row("select unixepoch(max(last_updated)) from feed", [], |row| row.get(0))
-        .unwrap_or(0)
+pub fn get_last_refreshed_time(tx: &Transaction) -> Result<u64, rusqlite::Error> {
+    // null when there are no feeds yet
+    let last_refreshed: Option<u64> =
+        tx.query_row("select unixepoch(max(last_updated)) from feed", [], |row| row.get(0))?;
+    Ok(last_refreshed.unwrap_or(0))
 }
 
-pub fn get_feed_id_by_url(tx: &Transaction, url: &str) -> Option<u64> {
-    tx.query_row("SELECT feed_id from feed_url where url = ?1 ", [&url], |row| row.get(0))
-        .ok()
+pub fn get_feed_id_by_url(tx: &Transaction, url: &str) -> Result<Option<u64>, rusqlite::Error> {
+    tx.prepare_cached("SELECT feed_id from feed_url where url = ?1 ")?
+        .query_row([&url], |row| row.get(0))
+        .optional()
 }
 
-pub fn get_all_feeds(tx: &Transaction) -> Option<Vec<(Feed, FeedUrl)>> {
+pub fn get_all_feeds(tx: &Transaction) -> Result<Vec<(Feed, FeedUrl)>, rusqlite::Error> {
     let get_all_feeds_statement = tx.prepare(
         "with f as ( \
-            select feed.id, feed.title, feed.last_updated, max(feed_url.id) as feed_url_id \
+            select feed.id, feed.title, feed.site_url, feed.last_updated, max(feed_url.id) as feed_url_id \
             from feed join feed_url on feed.id = feed_url.feed_id group by feed.id \
-        ) select f.id, f.title, unixepoch(f.last_updated), u.id, u.url from f join feed_url u on f.feed_url_id = u.id",
+        ) select f.id, f.title, unixepoch(f.last_updated), u.id, u.url, f.site_url \
+        from f join feed_url u on f.feed_url_id = u.id",
     );
 
-    let all_feeds: Result<Vec<(Feed, FeedUrl)>, _> = get_all_feeds_statement
-        .ok()?
+    get_all_feeds_statement?
         .query_map([], |row| {
             Ok((
                 Feed {
                     id: row.get(0)?,
                     title: row.get(1)?,
+                    site_url: row.get(5)?,
                     last_updated: row.get(2)?,
                 },
                 FeedUrl {
@@ -48,34 +54,114 @@ pub fn get_all_feeds(tx: &Transaction) -> Option<Vec<(Feed, FeedUrl)>> {
                     url: row.get(4)?,
                 },
             ))
-        })
-        .ok()?
-        .collect();
-    all_feeds.ok()
+        })?
+        .collect()
 }
 
-pub fn upsert_feed(tx: &Transaction, url: &str, title: Option<&str>) -> (u64, u64, bool) {
-    if let Some(feed_id) = get_feed_id_by_url(tx, url) {
-        let last_updated = tx
-            .query_row(
-                "update feed set title = iif(?1 is null, title, ?2), last_updated = datetime() where id = ?3 returning unixepoch(last_updated)",
-                rusqlite::params![title, title, feed_id],
-                |row| row.get(0),
-            )
-            .unwrap_or(0);
-        (feed_id, last_updated, false)
-    } else if let Ok(feed_id) = tx.query_row("insert into feed (title) values (?1) returning id", [&title], |row| {
-        row.get::<usize, u64>(0)
-    }) {
-        let feed_url_id = tx
-            .query_row(
-                "insert into feed_url (feed_id, url) values (?1, ?2) returning id",
-                rusqlite::params![feed_id, url],
-                |row| row.get(0),
-            )
-            .unwrap_or(0);
-        (feed_id, feed_url_id, true)
+pub fn upsert_feed(tx: &Transaction, url: &str, title: Option<&str>) -> Result<(u64, u64, bool), rusqlite::Error> {
+    if let Some(feed_id) = get_feed_id_by_url(tx, url)? {
+        let last_updated = tx.query_row(
+            "update feed set title = iif(?1 is null, title, ?2), last_updated = datetime() where id = ?3 returning unixepoch(last_updated)",
+            rusqlite::params![title, title, feed_id],
+            |row| row.get(0),
+        )?;
+        Ok((feed_id, last_updated, false))
     } else {
-        (0, 0, false)
+        let feed_id: u64 = tx.query_row("insert into feed (title) values (?1) returning id", [&title], |row| {
+            row.get(0)
+        })?;
+        let feed_url_id = tx.query_row(
+            "insert into feed_url (feed_id, url) values (?1, ?2) returning id",
+            rusqlite::params![feed_id, url],
+            |row| row.get(0),
+        )?;
+        Ok((feed_id, feed_url_id, true))
     }
 }
+
+pub fn set_site_url(tx: &Transaction, feed_id: u64, site_url: &str) -> Result<usize, rusqlite::Error> {
+    tx.execute(
+        "update feed set site_url = ?1 where id = ?2",
+        rusqlite::params![site_url, feed_id],
+    )
+}
+
+pub fn add_feed_url(tx: &Transaction, feed_id: u64, url: &str) -> Result<bool, rusqlite::Error> {
+    let inserted = tx.execute(
+        "insert or ignore into feed_url (feed_id, url) values (?1, ?2)",
+        rusqlite::params![feed_id, url],
+    )?;
+    Ok(inserted > 0)
+}
+
+pub fn get_all_feed_urls(tx: &Transaction) -> Result<Vec<FeedUrl>, rusqlite::Error> {
+    tx.prepare("select id, feed_id, url from feed_url order by id desc")?
+        .query_map([], |row| {
+            Ok(FeedUrl {
+                id: row.get(0)?,
+                feed_id: row.get(1)?,
+                url: row.get(2)?,
+            })
+        })?
+        .collect()
+}
+
+pub fn feed_exists(tx: &Transaction, feed_id: u64) -> Result<bool, rusqlite::Error> {
+    tx.query_row("select 1 from feed where id = ?1", [feed_id], |_| Ok(()))
+        .optional()
+        .map(|v| v.is_some())
+}
+
+pub fn rename_feed(tx: &Transaction, feed_id: u64, title: &str) -> Result<usize, rusqlite::Error> {
+    tx.execute(
+        "update feed set title = ?1 where id = ?2",
+        rusqlite::params![title, feed_id],
+    )
+}
+
+pub fn set_feed_url(tx: &Transaction, feed_id: u64, url: &str) -> Result<usize, rusqlite::Error> {
+    // the feed_url with the largest id is the one being fetched, so move the url to the end
+    tx.execute(
+        "delete from feed_url where feed_id = ?1 and url = ?2",
+        rusqlite::params![feed_id, url],
+    )?;
+    tx.execute(
+        "insert into feed_url (feed_id, url) values (?1, ?2)",
+        rusqlite::params![feed_id, url],
+    )
+}
+
+pub fn delete_feed_url(tx: &Transaction, feed_id: u64, url_id: u64) -> Result<usize, rusqlite::Error> {
+    tx.execute(
+        "delete from feed_url where id = ?1 and feed_id = ?2 \
+        and (select count(*) from feed_url where feed_id = ?2) > 1",
+        [url_id, feed_id],
+    )
+}
+
+pub fn delete_feed(tx: &Transaction, feed_id: u64) -> Result<usize, rusqlite::Error> {
+    tx.execute(
+        "delete from blob_storage where item_id in (select id from item where feed_id = ?1)",
+        [feed_id],
+    )?;
+    tx.execute(
+        "delete from item_tag where item_id in (select id from item where feed_id = ?1)",
+        [feed_id],
+    )?;
+    tx.execute(
+        "delete from item_revision where item_id in (select id from item where feed_id = ?1)",
+        [feed_id],
+    )?;
+    tx.execute("delete from item where feed_id = ?1", [feed_id])?;
+    tx.execute("delete from feed_group where feed_id = ?1", [feed_id])?;
+    groups::delete_empty_groups(tx)?;
+    tx.execute(
+        "delete from push_rule where target = 'feed' and target_id = ?1",
+        [feed_id],
+    )?;
+    tx.execute("delete from retention_rule where feed_id = ?1", [feed_id])?;
+    tx.execute("delete from pruned_item where feed_id = ?1", [feed_id])?;
+    tx.execute("delete from favicon where feed_id = ?1", [feed_id])?;
+    tx.execute("delete from feed_url where feed_id = ?1", [feed_id])?;
+    tx.execute("delete from feed where id = ?1", [feed_id])
+}
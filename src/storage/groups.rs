@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use rusqlite::{OptionalExtension, Transaction};
+
+#[derive(Debug)]
+pub struct Group {
+    pub id: u64,
+    pub title: String,
+}
+
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct FeedGroup {
+    pub id: u64,
+    pub feed_id: u64,
+    pub group_id: u64,
+}
+
+pub fn get_all_groups(tx: &Transaction) -> Result<Vec<Group>, rusqlite::Error> {
+    tx.prepare("select id, title from \"group\" order by title")?
+        .query_map([], |row| {
+            Ok(Group {
+                id: row.get(0)?,
+                title: row.get(1)?,
+            })
+        })?
+        .collect()
+}
+
+pub fn get_all_feed_groups(tx: &Transaction) -> Result<Vec<FeedGroup>, rusqlite::Error> {
+    tx.prepare("select id, feed_id, group_id from feed_group order by group_id, feed_id")?
+        .query_map([], |row| {
+            Ok(FeedGroup {
+                id: row.get(0)?,
+                feed_id: row.get(1)?,
+                group_id: row.get(2)?,
+            })
+        })?
+        .collect()
+}
+
+pub fn get_feed_group_titles(tx: &Transaction) -> Result<HashMap<u64, Vec<String>>, rusqlite::Error> {
+    let titles: HashMap<u64, String> = get_all_groups(tx)?
+        .into_iter()
+        .map(|group| (group.id, group.title))
+        .collect();
+    let mut feed_group_titles: HashMap<u64, Vec<String>> = HashMap::new();
+    for feed_group in get_all_feed_groups(tx)? {
+        if let Some(title) = titles.get(&feed_group.group_id) {
+            feed_group_titles
+                .entry(feed_group.feed_id)
+                .or_default()
+                .push(title.to_owned());
+        }
+    }
+    Ok(feed_group_titles)
+}
+
+pub fn get_group_id_by_title(tx: &Transaction, title: &str) -> Result<Option<u64>, rusqlite::Error> {
+    tx.query_row("select id from \"group\" where title = ?1", [title], |row| row.get(0))
+        .optional()
+}
+
+pub fn upsert_group(tx: &Transaction, title: &str) -> Result<u64, rusqlite::Error> {
+    tx.query_row(
+        "insert into \"group\" (title) values (?1) on conflict (title) do update set title = excluded.title returning id",
+        [title],
+        |row| row.get(0),
+    )
+}
+
+pub fn add_feed_group(tx: &Transaction, feed_id: u64, title: &str) -> Result<usize, rusqlite::Error> {
+    let group_id = upsert_group(tx, title)?;
+    tx.execute(
+        "insert or ignore into feed_group (feed_id, group_id) values (?1, ?2)",
+        [feed_id, group_id],
+    )
+}
+
+pub fn set_feed_groups(tx: &Transaction, feed_id: u64, titles: &[String]) -> Result<(), rusqlite::Error> {
+    tx.execute("delete from feed_group where feed_id = ?1", [feed_id])?;
+    for title in titles {
+        add_feed_group(tx, feed_id, title)?;
+    }
+    delete_empty_groups(tx)
+}
+
+pub fn delete_empty_groups(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    // groups are only defined by their feeds
+    tx.execute(
+        "delete from \"group\" where id not in (select group_id from feed_group)",
+        [],
+    )?;
+    tx.execute(
+        "delete from push_rule where target = 'group' and target_id not in (select id from \"group\")",
+        [],
+    )?;
+    Ok(())
+}
@@ -1,6 +1,12 @@
-use rusqlite::Transaction;
+use openssl::hash::{MessageDigest, hash};
+use rusqlite::{OptionalExtension, Row, Transaction, types::Value};
 use serde::Serialize;
 
+use crate::storage::revisions;
+
+const ITEM_COLUMNS: &str =
+    "id, feed_id, title, author, url, content, is_saved, is_read, counter, unixepoch(create_time)";
+
 #[derive(Serialize, Debug)]
 pub struct Item {
     pub id: u64,
@@ -15,6 +21,49 @@ pub struct Item {
     pub created_on_time: u64,
 }
 
+#[derive(Debug)]
+pub struct SearchResult {
+    pub item: Item,
+    pub snippet: String, // cut from html, matches are marked with \x02 and \x03
+    pub rank: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct ItemFilter {
+    pub feed_id: Option<u64>,
+    pub label: Option<String>, // group title or item tag
+    pub is_read: Option<u8>,
+    pub is_saved: Option<u8>,
+    pub newer_than: Option<u64>,
+    pub older_than: Option<u64>,
+    pub oldest_first: bool,
+    pub limit: u64,
+    pub offset: u64,
+}
+
+fn to_item(row: &Row) -> rusqlite::Result<Item> {
+    Ok(Item {
+        id: row.get(0)?,
+        feed_id: row.get(1)?,
+        title: row.get(2)?,
+        author: row.get(3)?,
+        url: row.get(4)?,
+        html: row.get(5)?,
+        is_saved: row.get(6)?,
+        is_read: row.get(7)?,
+        counter: row.get(8)?,
+        created_on_time: row.get(9)?,
+    })
+}
+
+/// Hash of the fields compared to detect updated items, empty if hashing fails
+pub fn content_hash(title: &str, html: &str) -> String {
+    let input = format!("{}\0{}", title, html);
+    hash(MessageDigest::sha256(), input.as_bytes())
+        .map(|digest| digest.iter().map(|b| format!("{b:02x}")).collect())
+        .unwrap_or_default()
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn create_item(
     tx: &Transaction,
@@ -25,95 +74,245 @@ pub fn create_item(
     url: &str,
     author: &str,
     created_at: u64,
-) -> (u64, bool) {
-    if let Ok(existing_id) = tx.query_row(
-        "select id from item where feed_id = ?1 and guid = ?2",
-        rusqlite::params![feed_id, guid],
-        |row| row.get(0),
-    ) {
-        return (existing_id, false);
-    }
-    if let Ok(new_id) = tx.query_row(
-        "insert into item (feed_id, guid, title, author, content, url, create_time) \
-        values (?1, ?2, ?3, ?4, ?5, ?6, datetime(?7, 'unixepoch')) returning id",
-        rusqlite::params![feed_id, guid, title, author, html, url, created_at],
-        |row| row.get(0),
-    ) {
-        return (new_id, true);
+) -> Result<(u64, bool), rusqlite::Error> {
+    let existing_id: Option<u64> = tx
+        .prepare_cached("select id from item where feed_id = ?1 and guid = ?2")?
+        .query_row(rusqlite::params![feed_id, guid], |row| row.get(0))
+        .optional()?;
+    if let Some(existing_id) = existing_id {
+        return Ok((existing_id, false));
     }
-    (0, true)
+    let new_id = tx
+        .prepare_cached(
+            "insert into item (feed_id, guid, title, author, content, url, create_time, content_hash) \
+            values (?1, ?2, ?3, ?4, ?5, ?6, datetime(?7, 'unixepoch'), ?8) returning id",
+        )?
+        .query_row(
+            rusqlite::params![
+                feed_id,
+                guid,
+                title,
+                author,
+                html,
+                url,
+                created_at,
+                content_hash(title, html)
+            ],
+            |row| row.get(0),
+        )?;
+    Ok((new_id, true))
 }
 
-pub fn set_item_read_status(tx: &Transaction, id: &str, status: &str) {
-    if let Err(e) = tx.execute("update item set is_read = ?1 where id = ?2", [status, id]) {
-        println!("!! error setting item read status: {e}")
+/// Updates title and content of an existing item if either changed, keeping the previous version as a revision
+pub fn update_item(
+    tx: &Transaction,
+    id: u64,
+    title: &str,
+    html: &str,
+    mark_unread: bool,
+) -> Result<bool, rusqlite::Error> {
+    let (saved_title, saved_html, saved_hash): (String, String, String) = tx
+        .prepare_cached("select title, content, content_hash from item where id = ?1")?
+        .query_row([id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+    let new_hash = content_hash(title, html);
+    // items saved without a hash are compared by their saved fields once
+    let saved_hash = if saved_hash.is_empty() {
+        content_hash(&saved_title, &saved_html)
+    } else {
+        saved_hash
+    };
+    if new_hash == saved_hash {
+        tx.prepare_cached("update item set content_hash = ?1 where id = ?2 and content_hash = ''")?
+            .execute(rusqlite::params![new_hash, id])?;
+        return Ok(false);
     }
+    revisions::save_revision(tx, id)?;
+    tx.prepare_cached(
+        "update item set title = ?1, content = ?2, content_hash = ?3, update_time = current_timestamp, \
+        is_read = iif(?4, 0, is_read) where id = ?5",
+    )?
+    .execute(rusqlite::params![title, html, new_hash, mark_unread, id])?;
+    Ok(true)
+}
+
+pub fn set_item_read_status(tx: &Transaction, id: &str, status: &str) -> Result<usize, rusqlite::Error> {
+    tx.execute("update item set is_read = ?1 where id = ?2", [status, id])
+}
+
+pub fn set_item_saved_status(tx: &Transaction, id: &str, status: &str) -> Result<usize, rusqlite::Error> {
+    tx.execute("update item set is_saved = ?1 where id = ?2", [status, id])
 }
 
-pub fn set_item_saved_status(tx: &Transaction, id: &str, status: &str) {
-    if let Err(e) = tx.execute("update item set is_saved = ?1 where id = ?2", [status, id]) {
-        println!("!! error setting item saved status: {e}")
+pub fn set_items_read_status_before(
+    tx: &Transaction,
+    filter_op: &str,
+    filter_id: u64,
+    before: u64,
+) -> Result<usize, rusqlite::Error> {
+    let statement = "update item set is_read = 1 where is_read = 0 and unixepoch(create_time) <= ?1";
+    if filter_op == "feed" {
+        tx.execute(&format!("{statement} and feed_id = ?2"), [before, filter_id])
+    } else if filter_op == "group" {
+        tx.execute(
+            &format!("{statement} and feed_id in (select feed_id from feed_group where group_id = ?2)"),
+            [before, filter_id],
+        )
+    } else {
+        tx.execute(statement, [before])
     }
 }
 
-pub fn get_items(tx: &Transaction, filter_op: &str, filter_arg: &str) -> Option<Vec<Item>> {
+pub fn get_items(
+    tx: &Transaction,
+    filter_op: &str,
+    filter_arg: &str,
+    limit: u64,
+) -> Result<Vec<Item>, rusqlite::Error> {
     // validation for filter_arg
     for x in filter_arg.split(",") {
         if let Err(e) = x.parse::<u64>() {
             println!("!! parse argument failed for get_items: {filter_arg} ({e})");
-            return None;
+            return Ok(vec![]);
         }
     }
 
+    // since_id pages forward from the oldest item, max_id pages backward from the newest one
     let statement = format!(
-        "select {} from item {} limit 50", // make cargo fmt happy again
-        "id, feed_id, title, author, url, content, is_saved, is_read, counter, unixepoch(create_time)",
+        "select {} from item {} limit {limit}", // make cargo fmt happy again
+        ITEM_COLUMNS,
         if filter_op == "with_ids" {
-            format!("where id in ({filter_arg})")
+            format!("where id in ({filter_arg}) order by id")
         } else if filter_op == "since_id" {
-            format!("where id > {filter_arg}")
+            format!("where id > {filter_arg} order by id")
+        } else if filter_op == "max_id" {
+            format!("where id < {filter_arg} order by id desc")
         } else {
-            String::new() // todo: check if everything should be pulled here
+            "order by id desc".to_owned()
         }
     );
 
-    let result: Result<Vec<Item>, _> = tx
-        .prepare(&statement)
-        .ok()?
-        .query_map([], |row| {
-            Ok(Item {
-                id: row.get(0)?,
-                feed_id: row.get(1)?,
-                title: row.get(2)?,
-                author: row.get(3)?,
-                url: row.get(4)?,
-                html: row.get(5)?,
-                is_saved: row.get(6)?,
-                is_read: row.get(7)?,
-                counter: row.get(8)?,
-                created_on_time: row.get(9)?,
-            })
-        })
-        .ok()?
-        .collect();
-    result.ok()
+    tx.prepare(&statement)?.query_map([], to_item)?.collect()
+}
+
+fn build_item_filter(filter: &ItemFilter) -> (String, Vec<Value>) {
+    let mut conditions: Vec<&str> = vec![];
+    let mut params: Vec<Value> = vec![];
+    if let Some(feed_id) = filter.feed_id {
+        conditions.push("feed_id = ?");
+        params.push(Value::Integer(feed_id as i64));
+    }
+    if let Some(label) = &filter.label {
+        conditions.push(
+            "(feed_id in (select feed_id from feed_group join \"group\" on \"group\".id = feed_group.group_id \
+            where \"group\".title = ?) or id in (select item_id from item_tag where tag = ?))",
+        );
+        params.push(Value::Text(label.to_owned()));
+        params.push(Value::Text(label.to_owned()));
+    }
+    if let Some(is_read) = filter.is_read {
+        conditions.push("is_read = ?");
+        params.push(Value::Integer(is_read as i64));
+    }
+    if let Some(is_saved) = filter.is_saved {
+        conditions.push("is_saved = ?");
+        params.push(Value::Integer(is_saved as i64));
+    }
+    if let Some(newer_than) = filter.newer_than {
+        conditions.push("unixepoch(create_time) >= ?");
+        params.push(Value::Integer(newer_than as i64));
+    }
+    if let Some(older_than) = filter.older_than {
+        conditions.push("unixepoch(create_time) <= ?");
+        params.push(Value::Integer(older_than as i64));
+    }
+    let statement = format!(
+        "{} order by create_time {order}, id {order} limit {} offset {}",
+        if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("where {}", conditions.join(" and "))
+        },
+        filter.limit,
+        filter.offset,
+        order = if filter.oldest_first { "asc" } else { "desc" },
+    );
+    (statement, params)
+}
+
+pub fn get_filtered_items(tx: &Transaction, filter: &ItemFilter) -> Result<Vec<Item>, rusqlite::Error> {
+    let (statement, params) = build_item_filter(filter);
+    tx.prepare(&format!("select {ITEM_COLUMNS} from item {statement}"))?
+        .query_map(rusqlite::params_from_iter(params), to_item)?
+        .collect()
+}
+
+pub fn get_filtered_item_ids(tx: &Transaction, filter: &ItemFilter) -> Result<Vec<(u64, u64)>, rusqlite::Error> {
+    let (statement, params) = build_item_filter(filter);
+    tx.prepare(&format!("select id, unixepoch(create_time) from item {statement}"))?
+        .query_map(rusqlite::params_from_iter(params), |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect()
+}
+
+pub fn get_unread_counts(tx: &Transaction) -> Result<Vec<(u64, u64, u64)>, rusqlite::Error> {
+    tx.prepare("select feed_id, count(*), max(unixepoch(create_time)) from item where is_read = 0 group by feed_id")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect()
 }
 
-pub fn get_total_items(tx: &Transaction, extra_filter: &str) -> u64 {
+pub fn get_total_items(tx: &Transaction, extra_filter: &str) -> Result<u64, rusqlite::Error> {
     tx.query_row(&format!("select count(*) from item {extra_filter}"), [], |row| {
         row.get(0)
     })
-    .unwrap_or(0)
 }
 
-pub fn get_unread_item_ids(tx: &Transaction) -> Option<Vec<u64>> {
-    let unread = tx.prepare("select id from item where is_read = 0");
-    let ids: Result<Vec<u64>, _> = unread.ok()?.query_map([], |row| row.get(0)).ok()?.collect();
-    ids.ok()
+pub fn get_unread_item_ids(tx: &Transaction) -> Result<Vec<u64>, rusqlite::Error> {
+    tx.prepare("select id from item where is_read = 0")?
+        .query_map([], |row| row.get(0))?
+        .collect()
+}
+
+pub fn get_saved_item_ids(tx: &Transaction) -> Result<Vec<u64>, rusqlite::Error> {
+    tx.prepare("select id from item where is_saved = 1")?
+        .query_map([], |row| row.get(0))?
+        .collect()
+}
+
+fn build_search_query(query: &str) -> String {
+    // every word is quoted as a phrase, so user input can not break fts5 query syntax;
+    // words shorter than 3 characters can not be matched by the trigram tokenizer
+    query
+        .split_whitespace()
+        .filter(|v| v.chars().count() >= 3)
+        .map(|v| format!("\"{}\"", v.replace('"', "\"\"")))
+        .collect::<Vec<String>>()
+        .join(" ")
 }
 
-pub fn get_saved_item_ids(tx: &Transaction) -> Option<Vec<u64>> {
-    let saved = tx.prepare("select id from item where is_saved = 1");
-    let ids: Result<Vec<u64>, _> = saved.ok()?.query_map([], |row| row.get(0)).ok()?.collect();
-    ids.ok()
+pub fn search_items(
+    tx: &Transaction,
+    query: &str,
+    limit: u64,
+    offset: u64,
+) -> Result<Vec<SearchResult>, rusqlite::Error> {
+    let search_query = build_search_query(query);
+    if search_query.is_empty() {
+        return Ok(vec![]);
+    }
+    // titles weigh more than contents in ranking
+    let statement = format!(
+        "select {ITEM_COLUMNS}, s.snippet, s.rank from item join ( \
+            select rowid, snippet(item_search, -1, char(2), char(3), '…', 64) as snippet, \
+            bm25(item_search, 5.0, 1.0) as rank from item_search where item_search match ?1 \
+            order by rank limit ?2 offset ?3 \
+        ) s on item.id = s.rowid order by s.rank"
+    );
+    tx.prepare(&statement)?
+        .query_map(rusqlite::params![search_query, limit, offset], |row| {
+            Ok(SearchResult {
+                item: to_item(row)?,
+                snippet: row.get(10)?,
+                rank: row.get(11)?,
+            })
+        })?
+        .collect()
 }
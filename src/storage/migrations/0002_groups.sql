@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS "group"
+(
+    id    integer      not null
+        primary key,
+    title varchar(255) not null
+        constraint uniq_title
+            unique
+);
+CREATE TABLE IF NOT EXISTS "feed_group"
+(
+    id       integer not null
+        primary key,
+    feed_id  integer not null
+        references feed,
+    group_id integer not null
+        references "group",
+    constraint uniq_feed_id_group_id
+        unique (feed_id, group_id)
+);
@@ -0,0 +1,10 @@
+ALTER TABLE "feed" ADD COLUMN site_url varchar(255) default '' not null;
+CREATE TABLE IF NOT EXISTS "favicon"
+(
+    feed_id      integer                            not null
+        primary key
+        references feed,
+    url          varchar(255)                       not null,
+    data         text                               not null,
+    last_updated datetime default CURRENT_TIMESTAMP not null
+);
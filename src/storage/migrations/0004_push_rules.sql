@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS "push_rule"
+(
+    id          integer                     not null
+        primary key,
+    target      varchar(16)                 not null, -- 'feed' or 'group'
+    target_id   integer                     not null,
+    mode        varchar(16) default 'always' not null, -- 'always', 'muted' or 'keyword'
+    keywords    text        default ''      not null, -- one keyword per line
+    quiet_start varchar(5)  default ''      not null, -- local time as HH:MM
+    quiet_end   varchar(5)  default ''      not null,
+    unique (target, target_id)
+);
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS "push_outbox"
+(
+    id           integer                               not null
+        primary key,
+    destination  varchar(255)                          not null, -- `kind+url` of the push backend
+    notification text                                  not null, -- json
+    status       varchar(16) default 'pending'         not null, -- 'pending' or 'failed'
+    attempts     integer     default 1                 not null,
+    last_error   text        default ''                not null,
+    next_attempt datetime    default CURRENT_TIMESTAMP not null,
+    create_time  datetime    default CURRENT_TIMESTAMP not null
+);
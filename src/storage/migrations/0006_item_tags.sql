@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS "item_tag"
+(
+    id      integer      not null
+        primary key,
+    item_id integer      not null
+        references item,
+    tag     varchar(255) not null,
+    unique (item_id, tag)
+);
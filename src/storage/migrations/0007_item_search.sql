@@ -0,0 +1,27 @@
+-- external content index of item, trigram tokenizer to match substrings of CJK text (3 characters at least)
+CREATE VIRTUAL TABLE IF NOT EXISTS "item_search" USING fts5
+(
+    title,
+    content,
+    content = 'item',
+    content_rowid = 'id',
+    tokenize = 'trigram'
+);
+
+INSERT INTO item_search(item_search) VALUES ('rebuild');
+
+CREATE TRIGGER IF NOT EXISTS item_search_insert AFTER INSERT ON item
+BEGIN
+    INSERT INTO item_search(rowid, title, content) VALUES (new.id, new.title, new.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS item_search_delete AFTER DELETE ON item
+BEGIN
+    INSERT INTO item_search(item_search, rowid, title, content) VALUES ('delete', old.id, old.title, old.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS item_search_update AFTER UPDATE OF title, content ON item
+BEGIN
+    INSERT INTO item_search(item_search, rowid, title, content) VALUES ('delete', old.id, old.title, old.content);
+    INSERT INTO item_search(rowid, title, content) VALUES (new.id, new.title, new.content);
+END;
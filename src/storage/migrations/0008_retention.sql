@@ -0,0 +1,24 @@
+CREATE TABLE IF NOT EXISTS "retention_rule"
+(
+    id             integer           not null
+        primary key,
+    feed_id        integer           not null
+        unique
+        references feed,
+    max_age        integer,                   -- days, null for `--max-age`
+    max_items      integer,                   -- null for `--max-items`
+    keep_saved     integer default 1 not null,
+    keep_commented integer default 1 not null -- items with comments in blob_storage
+);
+
+-- guids of pruned items, so they are not created again while still in the upstream feed
+CREATE TABLE IF NOT EXISTS "pruned_item"
+(
+    id          integer                            not null
+        primary key,
+    feed_id     integer                            not null
+        references feed,
+    guid        varchar(255)                       not null,
+    create_time datetime default CURRENT_TIMESTAMP not null,
+    unique (feed_id, guid)
+);
@@ -0,0 +1,16 @@
+-- sha256 of title and content, empty for items saved before this migration until they are fetched again
+ALTER TABLE "item" ADD COLUMN content_hash varchar(64) default '' not null;
+
+-- previous versions of items whose title or content changed upstream
+CREATE TABLE IF NOT EXISTS "item_revision"
+(
+    id          integer                            not null
+        primary key,
+    item_id     integer                            not null
+        references item,
+    title       varchar(255)                       not null,
+    content     text                               not null,
+    create_time datetime default CURRENT_TIMESTAMP not null -- when the revision was replaced
+);
+
+CREATE INDEX IF NOT EXISTS idx_item_revision_item_id ON item_revision (item_id);
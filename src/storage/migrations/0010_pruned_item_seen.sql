@@ -0,0 +1,4 @@
+-- last time a pruned guid was still in the upstream feed, it is forgotten once it has been gone for the retention window
+ALTER TABLE "pruned_item" ADD COLUMN seen_time datetime;
+
+UPDATE "pruned_item" SET seen_time = create_time;
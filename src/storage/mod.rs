@@ -1,10 +1,78 @@
-use rusqlite::{Connection, Transaction, fallible_iterator::FallibleIterator, types::Value};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use r2d2::{Pool, PooledConnection};
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{
+    Connection, OpenFlags, Transaction, TransactionBehavior, fallible_iterator::FallibleIterator, types::Value,
+};
+
+use crate::common::PipeError;
+use crate::metrics;
 
 pub mod blob;
+pub mod favicons;
 pub mod feeds;
+pub mod groups;
 pub mod items;
+pub mod push_outbox;
+pub mod push_rules;
+pub mod retention;
+pub mod revisions;
+pub mod tags;
 pub mod valine;
 
+// append only, each entry upgrades the schema by one `user_version`
+const MIGRATIONS: &[&str] = &[
+    include_str!("../../db.sql"),
+    include_str!("migrations/0002_groups.sql"),
+    include_str!("migrations/0003_favicons.sql"),
+    include_str!("migrations/0004_push_rules.sql"),
+    include_str!("migrations/0005_push_outbox.sql"),
+    include_str!("migrations/0006_item_tags.sql"),
+    include_str!("migrations/0007_item_search.sql"),
+    include_str!("migrations/0008_retention.sql"),
+    include_str!("migrations/0009_item_revisions.sql"),
+    include_str!("migrations/0010_pruned_item_seen.sql"),
+];
+
+const POOL_SIZE: u32 = 8;
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(2);
+const STATEMENT_CACHE_CAPACITY: usize = 64;
+
+static POOLS: LazyLock<Mutex<HashMap<String, Pool<SqliteConnectionManager>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn configure(conn: &mut Connection) -> Result<(), rusqlite::Error> {
+    // read transactions do not block the writer in wal mode, writers wait for each other up to the busy timeout
+    conn.pragma_update_and_check(None, "journal_mode", "wal", |_| Ok(()))?;
+    conn.pragma_update(None, "synchronous", "normal")?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+    Ok(())
+}
+
+fn get_connection(db: &str) -> Result<PooledConnection<SqliteConnectionManager>, PipeError> {
+    let pool = {
+        let mut pools = POOLS.lock().map_err(|_| PipeError::DatabasePoolPoisonedError)?;
+        match pools.get(db) {
+            Some(v) => v.clone(),
+            None => {
+                let manager = SqliteConnectionManager::file(db).with_init(configure);
+                let pool = Pool::builder()
+                    .max_size(POOL_SIZE)
+                    .connection_timeout(CONNECTION_TIMEOUT)
+                    .build(manager)?;
+                pools.insert(db.to_owned(), pool.clone());
+                pool
+            }
+        }
+    };
+    Ok(pool.get()?)
+}
+
 #[derive(Debug)]
 pub struct QueryResult {
     pub columns: Vec<String>,
@@ -12,7 +80,7 @@ pub struct QueryResult {
 }
 
 pub fn execute_query(db: &str, sql: &str) -> Result<QueryResult, Box<dyn std::error::Error>> {
-    let conn = Connection::open(db)?;
+    let conn = get_connection(db).map_err(|e| format!("{e:?}"))?;
     let mut stmt = conn.prepare(sql)?;
     let columns: Vec<String> = stmt.column_names().into_iter().map(|s| s.to_string()).collect();
     let rows: Vec<Vec<String>> = stmt
@@ -35,16 +103,89 @@ pub fn execute_query(db: &str, sql: &str) -> Result<QueryResult, Box<dyn std::er
     Ok(QueryResult { columns, rows })
 }
 
-pub fn transaction<T>(db: &str, callback: impl Fn(&Transaction) -> T) -> T {
-    let mut conn = Connection::open(db).unwrap();
-    let tx = conn.transaction().unwrap();
-    let result = callback(&tx);
-    if let Err(e) = tx.commit() {
-        println!("!! error committing transaction: {e}");
-    }
-    result
+pub fn query_values(db: &str, sql: &str, params: &[Value]) -> Result<(Vec<String>, Vec<Vec<Value>>), rusqlite::Error> {
+    // opened read only, queries from the pipe script can not modify anything
+    let conn = Connection::open_with_flags(db, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    let mut stmt = conn.prepare(sql)?;
+    let columns: Vec<String> = stmt.column_names().into_iter().map(|s| s.to_string()).collect();
+    let rows: Vec<Vec<Value>> = stmt
+        .query(rusqlite::params_from_iter(params))?
+        .map(|row| (0..columns.len()).map(|i| row.get(i)).collect())
+        .collect()?;
+    Ok((columns, rows))
 }
 
-pub fn migrations(_: &str) {
-    // todo: handle database migrations later
+fn run_transaction<T>(
+    db: &str,
+    behavior: TransactionBehavior,
+    callback: impl Fn(&Transaction) -> Result<T, PipeError>,
+) -> Result<T, PipeError> {
+    let mut conn = get_connection(db)?;
+    let tx = conn.transaction_with_behavior(behavior)?;
+    let result = callback(&tx)?;
+    tx.commit()?;
+    Ok(result)
+}
+
+/// Runs `callback` in one transaction, which is rolled back if the callback fails. Write transactions take the
+/// write lock upfront, a deferred transaction upgrading to write can not wait for the busy timeout. Waiting for
+/// a pooled connection or the lock blocks, so other tasks are moved off the calling worker thread meanwhile
+pub fn transaction_for<T>(
+    db: &str,
+    write: bool,
+    callback: impl Fn(&Transaction) -> Result<T, PipeError>,
+) -> Result<T, PipeError> {
+    let behavior = if write {
+        TransactionBehavior::Immediate
+    } else {
+        TransactionBehavior::Deferred
+    };
+    tokio::task::block_in_place(|| run_transaction(db, behavior, callback)).inspect_err(|e| {
+        if e.is_database_error() {
+            metrics::database_error();
+        }
+    })
+}
+
+/// Runs `callback` in a write transaction
+pub fn transaction<T>(db: &str, callback: impl Fn(&Transaction) -> Result<T, PipeError>) -> Result<T, PipeError> {
+    transaction_for(db, true, callback)
+}
+
+/// Runs `callback` in a read transaction, which must not write
+pub fn read_transaction<T>(db: &str, callback: impl Fn(&Transaction) -> Result<T, PipeError>) -> Result<T, PipeError> {
+    transaction_for(db, false, callback)
+}
+
+/// Releases free pages, incremental auto vacuum is enabled once by `migrations`
+pub fn vacuum(db: &str) -> Result<(), PipeError> {
+    Ok(get_connection(db)?.execute_batch("pragma incremental_vacuum")?)
+}
+
+pub fn migrations(db: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut conn = Connection::open(db)?;
+    let version: usize = conn.query_row("pragma user_version", [], |row| row.get(0))?;
+    if version > MIGRATIONS.len() {
+        return Err(format!(
+            "database schema version {version} is newer than supported version {}",
+            MIGRATIONS.len()
+        )
+        .into());
+    }
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+        tx.commit()?;
+        println!("applied database migration {}", index + 1);
+    }
+    let auto_vacuum: u8 = conn.query_row("pragma auto_vacuum", [], |row| row.get(0))?;
+    if auto_vacuum != 2 {
+        // switching an existing database to incremental auto vacuum takes one full vacuum, done before serving
+        println!("enabling incremental auto vacuum, this may take a while");
+        conn.pragma_update(None, "auto_vacuum", "incremental")?;
+        conn.execute_batch("vacuum")?;
+    }
+    Ok(())
 }
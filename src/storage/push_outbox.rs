@@ -0,0 +1,67 @@
+use rusqlite::Transaction;
+
+#[derive(Debug)]
+pub struct OutboxEntry {
+    pub id: u64,
+    pub destination: String,
+    pub notification: String,
+    pub attempts: u64,
+}
+
+pub fn add_outbox_entry(
+    tx: &Transaction,
+    destination: &str,
+    notification: &str,
+    error: &str,
+    delay: u64,
+) -> Result<usize, rusqlite::Error> {
+    tx.execute(
+        "insert into push_outbox (destination, notification, last_error, next_attempt) \
+        values (?1, ?2, ?3, datetime('now', '+' || ?4 || ' seconds'))",
+        rusqlite::params![destination, notification, error, delay],
+    )
+}
+
+pub fn get_due_outbox_entries(tx: &Transaction) -> Result<Vec<OutboxEntry>, rusqlite::Error> {
+    tx.prepare(
+        "select id, destination, notification, attempts from push_outbox \
+            where status = 'pending' and next_attempt <= datetime() order by id",
+    )?
+    .query_map([], |row| {
+        Ok(OutboxEntry {
+            id: row.get(0)?,
+            destination: row.get(1)?,
+            notification: row.get(2)?,
+            attempts: row.get(3)?,
+        })
+    })?
+    .collect()
+}
+
+pub fn delete_outbox_entry(tx: &Transaction, id: u64) -> Result<usize, rusqlite::Error> {
+    tx.execute("delete from push_outbox where id = ?1", [id])
+}
+
+pub fn reschedule_outbox_entry(tx: &Transaction, id: u64, error: &str, delay: u64) -> Result<usize, rusqlite::Error> {
+    tx.execute(
+        "update push_outbox set attempts = attempts + 1, last_error = ?1, \
+        next_attempt = datetime('now', '+' || ?2 || ' seconds') where id = ?3",
+        rusqlite::params![error, delay, id],
+    )
+}
+
+pub fn fail_outbox_entry(tx: &Transaction, id: u64, error: &str) -> Result<usize, rusqlite::Error> {
+    tx.execute(
+        "update push_outbox set status = 'failed', last_error = ?1 where id = ?2",
+        rusqlite::params![error, id],
+    )
+}
+
+pub fn get_outbox_counts(tx: &Transaction) -> Result<(u64, u64), rusqlite::Error> {
+    tx.query_row(
+        "select count(*) filter (where status = 'pending'), count(*) filter (where status = 'failed') \
+        from push_outbox",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+}
@@ -0,0 +1,69 @@
+use rusqlite::Transaction;
+
+#[derive(Debug, Clone)]
+pub struct PushRule {
+    pub target: String,
+    pub target_id: u64,
+    pub mode: String,
+    pub keywords: Vec<String>,
+    pub quiet_start: String,
+    pub quiet_end: String,
+}
+
+fn to_push_rule(row: &rusqlite::Row) -> Result<PushRule, rusqlite::Error> {
+    Ok(PushRule {
+        target: row.get(0)?,
+        target_id: row.get(1)?,
+        mode: row.get(2)?,
+        keywords: row
+            .get::<usize, String>(3)?
+            .lines()
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_owned())
+            .collect(),
+        quiet_start: row.get(4)?,
+        quiet_end: row.get(5)?,
+    })
+}
+
+pub fn get_all_push_rules(tx: &Transaction) -> Result<Vec<PushRule>, rusqlite::Error> {
+    tx.prepare("select target, target_id, mode, keywords, quiet_start, quiet_end from push_rule order by id")?
+        .query_map([], to_push_rule)?
+        .collect()
+}
+
+pub fn get_push_rules(tx: &Transaction, feed_id: u64) -> Result<Vec<PushRule>, rusqlite::Error> {
+    // the feed rule comes first, followed by rules of the groups the feed belongs to
+    tx.prepare(
+        "select target, target_id, mode, keywords, quiet_start, quiet_end from push_rule \
+            where (target = 'feed' and target_id = ?1) \
+            or (target = 'group' and target_id in (select group_id from feed_group where feed_id = ?1)) \
+            order by target = 'feed' desc, id",
+    )?
+    .query_map([feed_id], to_push_rule)?
+    .collect()
+}
+
+pub fn set_push_rule(tx: &Transaction, rule: &PushRule) -> Result<usize, rusqlite::Error> {
+    tx.execute(
+        "insert into push_rule (target, target_id, mode, keywords, quiet_start, quiet_end) \
+        values (?1, ?2, ?3, ?4, ?5, ?6) on conflict (target, target_id) do update set \
+        mode = excluded.mode, keywords = excluded.keywords, \
+        quiet_start = excluded.quiet_start, quiet_end = excluded.quiet_end",
+        rusqlite::params![
+            rule.target,
+            rule.target_id,
+            rule.mode,
+            rule.keywords.join("\n"),
+            rule.quiet_start,
+            rule.quiet_end
+        ],
+    )
+}
+
+pub fn delete_push_rule(tx: &Transaction, target: &str, target_id: u64) -> Result<usize, rusqlite::Error> {
+    tx.execute(
+        "delete from push_rule where target = ?1 and target_id = ?2",
+        rusqlite::params![target, target_id],
+    )
+}
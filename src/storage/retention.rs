@@ -0,0 +1,92 @@
+use rusqlite::Transaction;
+
+#[derive(Debug, Clone)]
+pub struct RetentionRule {
+    pub feed_id: u64,
+    pub max_age: Option<u64>,
+    pub max_items: Option<u64>,
+    pub keep_saved: bool,
+    pub keep_commented: bool,
+}
+
+pub fn get_all_retention_rules(tx: &Transaction) -> Result<Vec<RetentionRule>, rusqlite::Error> {
+    tx.prepare("select feed_id, max_age, max_items, keep_saved, keep_commented from retention_rule order by id")?
+        .query_map([], |row| {
+            Ok(RetentionRule {
+                feed_id: row.get(0)?,
+                max_age: row.get(1)?,
+                max_items: row.get(2)?,
+                keep_saved: row.get(3)?,
+                keep_commented: row.get(4)?,
+            })
+        })?
+        .collect()
+}
+
+pub fn set_retention_rule(tx: &Transaction, rule: &RetentionRule) -> Result<usize, rusqlite::Error> {
+    tx.execute(
+        "insert into retention_rule (feed_id, max_age, max_items, keep_saved, keep_commented) \
+        values (?1, ?2, ?3, ?4, ?5) on conflict (feed_id) do update set \
+        max_age = excluded.max_age, max_items = excluded.max_items, \
+        keep_saved = excluded.keep_saved, keep_commented = excluded.keep_commented",
+        rusqlite::params![
+            rule.feed_id,
+            rule.max_age,
+            rule.max_items,
+            rule.keep_saved,
+            rule.keep_commented
+        ],
+    )
+}
+
+pub fn delete_retention_rule(tx: &Transaction, feed_id: u64) -> Result<usize, rusqlite::Error> {
+    tx.execute("delete from retention_rule where feed_id = ?1", [feed_id])
+}
+
+/// Also records that the guid is still in the upstream feed, so it is not forgotten while it can come back
+pub fn is_pruned(tx: &Transaction, feed_id: u64, guid: &str) -> Result<bool, rusqlite::Error> {
+    tx.prepare_cached("update pruned_item set seen_time = CURRENT_TIMESTAMP where feed_id = ?1 and guid = ?2")?
+        .execute(rusqlite::params![feed_id, guid])
+        .map(|v| v > 0)
+}
+
+/// Deletes items older than `max_age` days or beyond the newest `max_items`, 0 disables either limit;
+/// items with a counter (used by valine) are always kept. Guids not seen upstream for `max_age` days
+/// are forgotten, without an age limit they are kept until the feed is deleted
+pub fn prune_feed_items(
+    tx: &Transaction,
+    feed_id: u64,
+    max_age: u64,
+    max_items: u64,
+    keep_saved: bool,
+    keep_commented: bool,
+) -> Result<usize, rusqlite::Error> {
+    if max_age == 0 && max_items == 0 {
+        return Ok(0);
+    }
+    if max_age > 0 {
+        tx.execute(
+            "delete from pruned_item where feed_id = ?1 and seen_time < datetime('now', '-' || ?2 || ' days')",
+            rusqlite::params![feed_id, max_age],
+        )?;
+    }
+    tx.execute("create temp table if not exists prune (id integer primary key)", [])?;
+    tx.execute("delete from prune", [])?;
+    tx.execute(
+        "insert into prune select id from item where feed_id = ?1 and counter = 0 \
+        and (?4 = 0 or is_saved = 0) \
+        and (?5 = 0 or id not in (select item_id from blob_storage)) \
+        and ((?2 > 0 and create_time < datetime('now', '-' || ?2 || ' days')) \
+        or (?3 > 0 and id not in (select id from item where feed_id = ?1 order by create_time desc, id desc limit ?3)))",
+        rusqlite::params![feed_id, max_age, max_items, keep_saved, keep_commented],
+    )?;
+    tx.execute(
+        "insert or ignore into pruned_item (feed_id, guid, seen_time) \
+        select feed_id, guid, CURRENT_TIMESTAMP from item where id in (select id from prune)",
+        [],
+    )?;
+    tx.execute("delete from blob_storage where item_id in (select id from prune)", [])?;
+    tx.execute("delete from item_tag where item_id in (select id from prune)", [])?;
+    tx.execute("delete from item_revision where item_id in (select id from prune)", [])?;
+    tx.execute("delete from item where id in (select id from prune)", [])
+}
@@ -0,0 +1,45 @@
+use rusqlite::Transaction;
+
+// older revisions of an item are dropped beyond this
+const MAX_REVISIONS: u64 = 20;
+
+#[derive(Debug)]
+pub struct ItemRevision {
+    pub id: u64,
+    pub item_id: u64,
+    pub title: String,
+    pub content: String,
+    pub create_time: u64,
+}
+
+fn to_item_revision(row: &rusqlite::Row) -> Result<ItemRevision, rusqlite::Error> {
+    Ok(ItemRevision {
+        id: row.get(0)?,
+        item_id: row.get(1)?,
+        title: row.get(2)?,
+        content: row.get(3)?,
+        create_time: row.get(4)?,
+    })
+}
+
+/// Saves the current title and content of an item before it is updated
+pub fn save_revision(tx: &Transaction, item_id: u64) -> Result<usize, rusqlite::Error> {
+    tx.prepare_cached(
+        "insert into item_revision (item_id, title, content) select id, title, content from item where id = ?1",
+    )?
+    .execute([item_id])?;
+    tx.prepare_cached(
+        "delete from item_revision where item_id = ?1 and id not in \
+        (select id from item_revision where item_id = ?1 order by id desc limit ?2)",
+    )?
+    .execute([item_id, MAX_REVISIONS])
+}
+
+pub fn get_revisions(tx: &Transaction, item_id: u64) -> Result<Vec<ItemRevision>, rusqlite::Error> {
+    tx.prepare(
+        "select id, item_id, title, content, unixepoch(create_time) from item_revision \
+        where item_id = ?1 order by id desc",
+    )?
+    .query_map([item_id], to_item_revision)?
+    .collect()
+}
@@ -0,0 +1,34 @@
+use std::collections::HashMap;
+
+use rusqlite::Transaction;
+
+pub fn set_item_tags(tx: &Transaction, item_id: u64, tags: &[String]) -> Result<(), rusqlite::Error> {
+    tx.prepare_cached("delete from item_tag where item_id = ?1")?
+        .execute([item_id])?;
+    for tag in tags.iter().filter(|v| !v.is_empty()) {
+        tx.prepare_cached("insert or ignore into item_tag (item_id, tag) values (?1, ?2)")?
+            .execute(rusqlite::params![item_id, tag])?;
+    }
+    Ok(())
+}
+
+pub fn get_item_tags(tx: &Transaction, item_ids: &[u64]) -> Result<HashMap<u64, Vec<String>>, rusqlite::Error> {
+    let mut item_tags: HashMap<u64, Vec<String>> = HashMap::new();
+    let statement = format!(
+        "select item_id, tag from item_tag where item_id in ({}) order by id",
+        item_ids.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(",")
+    );
+    let mut stmt = tx.prepare(&statement)?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<usize, u64>(0)?, row.get::<usize, String>(1)?)))?;
+    for row in rows {
+        let (item_id, tag) = row?;
+        item_tags.entry(item_id).or_default().push(tag);
+    }
+    Ok(item_tags)
+}
+
+pub fn get_all_tags(tx: &Transaction) -> Result<Vec<String>, rusqlite::Error> {
+    tx.prepare("select distinct tag from item_tag order by tag")?
+        .query_map([], |row| row.get(0))?
+        .collect()
+}
@@ -1,24 +1,29 @@
-use rusqlite::Transaction;
+use rusqlite::{OptionalExtension, Transaction};
 use url::Url;
 
 use crate::storage::blob::BlobStorage;
+use crate::storage::items;
 
-pub fn get_prefix(tx: &Transaction, id: u64) -> Option<String> {
-    let domain: String = tx
+pub fn get_prefix(tx: &Transaction, id: u64) -> Result<Option<String>, rusqlite::Error> {
+    let domain: Option<String> = tx
         .query_row("select url from feed_url where feed_id = ?1", [id], |row| row.get(0))
-        .ok()?;
-    let parsed = Url::parse(&domain).ok()?;
-    let scheme = parsed.scheme();
-    let host = parsed.host_str()?;
-    Some(format!("{}://{}", scheme, host))
+        .optional()?;
+    let prefix = domain.and_then(|v| {
+        let parsed = Url::parse(&v).ok()?;
+        Some(format!("{}://{}", parsed.scheme(), parsed.host_str()?))
+    });
+    Ok(prefix)
 }
 
-pub fn find_item_id_by_url(tx: &Transaction, feed_id: u64, url: &str) -> Option<u64> {
-    let prefix = get_prefix(tx, feed_id)?;
+pub fn find_item_id_by_url(tx: &Transaction, feed_id: u64, url: &str) -> Result<Option<u64>, rusqlite::Error> {
+    let prefix = match get_prefix(tx, feed_id)? {
+        Some(v) => v,
+        None => return Ok(None),
+    };
     tx.query_row("select id from item where url = ?1 limit 1", [prefix + url], |row| {
         row.get(0)
     })
-    .ok()
+    .optional()
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -31,83 +36,90 @@ pub fn refresh_existing_item(
     url: &str,
     author: &str,
     created_at: u64,
-) -> (u64, bool) {
-    let existing_id = tx.query_row(
+) -> Result<Option<u64>, rusqlite::Error> {
+    tx.prepare_cached(
         "update item set counter = 0, update_time = current_timestamp, \
-        guid = ?1, title = ?2, author = ?3, content = ?4, create_time = datetime(?5, 'unixepoch') \
-        where feed_id = ?6 and url = ?7 and title = '' and author = '' returning id",
-        rusqlite::params![guid, title, author, html, created_at, feed_id, url],
+            guid = ?1, title = ?2, author = ?3, content = ?4, create_time = datetime(?5, 'unixepoch'), \
+            content_hash = ?8 where feed_id = ?6 and url = ?7 and title = '' and author = '' returning id",
+    )?
+    .query_row(
+        rusqlite::params![
+            guid,
+            title,
+            author,
+            html,
+            created_at,
+            feed_id,
+            url,
+            items::content_hash(title, html)
+        ],
         |row| row.get(0),
-    );
-    match existing_id {
-        Ok(id) => (id, true),
-        Err(_) => (0, false),
-    }
+    )
+    .optional()
 }
 
-pub fn increment_item_counter(tx: &Transaction, id: u64) -> Option<usize> {
+pub fn increment_item_counter(tx: &Transaction, id: u64) -> Result<usize, rusqlite::Error> {
     tx.execute(
         "update item set counter = counter + 1, update_time = current_timestamp where id = ?",
         [id],
     )
-    .ok()
 }
 
-pub fn get_comment_count(tx: &Transaction, item_id: u64) -> u64 {
+pub fn get_comment_count(tx: &Transaction, item_id: u64) -> Result<u64, rusqlite::Error> {
     tx.query_row(
         "select count(*) from blob_storage where item_id = ?1 and reply_id is null",
         [item_id],
         |row| row.get(0),
     )
-    .unwrap_or(0)
 }
 
-pub fn get_comment_by_item_id(tx: &Transaction, item_id: u64, limit: u64, skip: u64) -> Option<Vec<BlobStorage>> {
-    let result: Result<Vec<BlobStorage>, _> = tx
-        .prepare(
-            "select id, item_id, reply_id, metadata, create_time, \
+pub fn get_comment_by_item_id(
+    tx: &Transaction,
+    item_id: u64,
+    limit: u64,
+    skip: u64,
+) -> Result<Vec<BlobStorage>, rusqlite::Error> {
+    tx.prepare(
+        "select id, item_id, reply_id, metadata, create_time, \
             iif(json_extract(metadata, '$.url') like '/%', cast(data as varchar), '') from blob_storage \
             where item_id = ?1 and reply_id is null order by id desc limit ?2 offset ?3",
-        )
-        .ok()?
-        .query_map([item_id, limit, skip], |row| {
-            Ok(BlobStorage {
-                id: row.get(0)?,
-                item_id: row.get(1)?,
-                reply_id: row.get(2)?,
-                metadata: row.get(3)?,
-                create_time: row.get(4)?,
-                data: row.get(5)?,
-            })
+    )?
+    .query_map([item_id, limit, skip], |row| {
+        Ok(BlobStorage {
+            id: row.get(0)?,
+            item_id: row.get(1)?,
+            reply_id: row.get(2)?,
+            metadata: row.get(3)?,
+            create_time: row.get(4)?,
+            data: row.get(5)?,
         })
-        .ok()?
-        .collect();
-    result.ok()
+    })?
+    .collect()
 }
 
-pub fn get_comment_by_reply_id(tx: &Transaction, feed_id: u64, reply_ids: &[String]) -> Option<Vec<BlobStorage>> {
+pub fn get_comment_by_reply_id(
+    tx: &Transaction,
+    feed_id: u64,
+    reply_ids: &[String],
+) -> Result<Vec<BlobStorage>, rusqlite::Error> {
     let reply_id_param = reply_ids.join("','");
-    let result: Result<Vec<BlobStorage>, _> = tx
-        .prepare(&format!(
-            "select id, item_id, reply_id, metadata, create_time, \
+    tx.prepare(&format!(
+        "select id, item_id, reply_id, metadata, create_time, \
             iif(json_extract(metadata, '$.url') like '/%', cast(data as varchar), '') from blob_storage \
             where reply_id in ('{}') and item_id in (select item_id from item where feed_id = ?1) order by id desc",
-            reply_id_param
-        ))
-        .ok()?
-        .query_map([feed_id], |row| {
-            Ok(BlobStorage {
-                id: row.get(0)?,
-                item_id: row.get(1)?,
-                reply_id: row.get(2)?,
-                metadata: row.get(3)?,
-                create_time: row.get(4)?,
-                data: row.get(5)?,
-            })
+        reply_id_param
+    ))?
+    .query_map([feed_id], |row| {
+        Ok(BlobStorage {
+            id: row.get(0)?,
+            item_id: row.get(1)?,
+            reply_id: row.get(2)?,
+            metadata: row.get(3)?,
+            create_time: row.get(4)?,
+            data: row.get(5)?,
         })
-        .ok()?
-        .collect();
-    result.ok()
+    })?
+    .collect()
 }
 
 pub fn save_comment(
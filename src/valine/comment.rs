@@ -115,29 +115,31 @@ impl valine::Valine {
             let data = c.data.as_deref().unwrap_or("");
             let url = c.metadata.url.as_deref().unwrap_or("");
             let v = storage::transaction(&self.db, |tx| {
-                storage::valine::find_item_id_by_url(tx, feed_id, url).and_then(|item_id| {
-                    storage::valine::save_comment(
-                        tx,
-                        item_id,
-                        c.reply_id.as_deref(),
-                        data,
-                        &serde_json::to_string(&c.metadata).unwrap_or("{}".to_owned()),
-                    )
-                    .ok()
-                })
-            })
+                let item_id = match storage::valine::find_item_id_by_url(tx, feed_id, url)? {
+                    Some(v) => v,
+                    None => return Ok(None),
+                };
+                let object_id = storage::valine::save_comment(
+                    tx,
+                    item_id,
+                    c.reply_id.as_deref(),
+                    data,
+                    &serde_json::to_string(&c.metadata).unwrap_or("{}".to_owned()),
+                )?;
+                Ok(Some(object_id))
+            })?
             .map(async |object_id| {
                 if url != self.path {
-                    push::bark::send_notification(
-                        &format!("New comment {}", url),
-                        "",
-                        data,
-                        "rss_pipe_valine",
-                        None, // todo: link
-                        None,
-                        &self.bark,
-                    )
-                    .await;
+                    self.push
+                        .send_notification(push::Notification::new(
+                            &format!("New comment {}", url),
+                            "",
+                            data,
+                            "rss_pipe_valine",
+                            None, // todo: link
+                            None,
+                        ))
+                        .await;
                 }
                 common::json_response(&format!(
                     "{{\"objectId\": \"{}\", \"createdAt\": \"{}\"}}",
@@ -156,11 +158,12 @@ impl valine::Valine {
         if url.is_empty() {
             common::not_found()
         } else {
-            let count = storage::transaction(&self.db, |tx| {
-                storage::valine::find_item_id_by_url(tx, feed_id, url).map_or(0, |id| {
-                    storage::valine::get_comment_count(tx, id) // make cargo fmt happy
-                })
-            });
+            let count = storage::read_transaction(&self.db, |tx| {
+                match storage::valine::find_item_id_by_url(tx, feed_id, url)? {
+                    Some(id) => Ok(storage::valine::get_comment_count(tx, id)?),
+                    None => Ok(0),
+                }
+            })?;
             common::json_response(&format!("{{\"results\": [], \"count\": {}}}", count))
         }
     }
@@ -177,11 +180,12 @@ impl valine::Valine {
         } else if limit == 0 {
             common::json_response("{\"results\": [], \"count\": 0}")
         } else {
-            let comments: Vec<Comment> = storage::transaction(&self.db, |tx| {
-                storage::valine::find_item_id_by_url(tx, feed_id, url).map_or(vec![], |id| {
-                    storage::valine::get_comment_by_item_id(tx, id, limit, skip).unwrap_or_default()
-                })
-            })
+            let comments: Vec<Comment> = storage::read_transaction(&self.db, |tx| {
+                match storage::valine::find_item_id_by_url(tx, feed_id, url)? {
+                    Some(id) => Ok(storage::valine::get_comment_by_item_id(tx, id, limit, skip)?),
+                    None => Ok(vec![]),
+                }
+            })?
             .iter()
             .map(to_comment)
             .collect();
@@ -196,9 +200,9 @@ impl valine::Valine {
         if rids.is_empty() {
             common::json_response("{\"results\": [], \"className\": \"Comment\"}")
         } else {
-            let comments: Vec<Comment> = storage::transaction(&self.db, |tx| {
-                storage::valine::get_comment_by_reply_id(tx, feed_id, rids).unwrap_or_default()
-            })
+            let comments: Vec<Comment> = storage::read_transaction(&self.db, |tx| {
+                Ok(storage::valine::get_comment_by_reply_id(tx, feed_id, rids)?)
+            })?
             .iter()
             .map(to_comment)
             .collect();
@@ -50,13 +50,15 @@ impl valine::Valine {
                 common::internal_server_error()
             } else {
                 let id = storage::transaction(&self.db, |tx| {
-                    storage::valine::get_prefix(tx, feed_id).map_or(0, |prefix| {
-                        let url = format!("{}{}", prefix, path);
-                        let (new_item_id, _) = storage::items::create_item(tx, feed_id, &url, "", "", &url, "", 0);
-                        storage::valine::increment_item_counter(tx, new_item_id);
-                        new_item_id
-                    })
-                });
+                    let prefix = match storage::valine::get_prefix(tx, feed_id)? {
+                        Some(v) => v,
+                        None => return Ok(0),
+                    };
+                    let url = format!("{}{}", prefix, path);
+                    let (new_item_id, _) = storage::items::create_item(tx, feed_id, &url, "", "", &url, "", 0)?;
+                    storage::valine::increment_item_counter(tx, new_item_id)?;
+                    Ok(new_item_id)
+                })?;
                 common::json_response(&format!(
                     "{{\"objectId\": \"{}\", \"createdAt\": \"{}\"}}",
                     id,
@@ -71,11 +73,12 @@ impl valine::Valine {
         if url.is_empty() {
             common::not_found()
         } else {
-            let counters: Vec<Counter> = storage::transaction(&self.db, |tx| {
-                storage::valine::find_item_id_by_url(tx, feed_id, &url).map_or(vec![], |c| {
-                    storage::items::get_items(tx, "with_ids", &c.to_string()).unwrap_or_default()
-                })
-            })
+            let counters: Vec<Counter> = storage::read_transaction(&self.db, |tx| {
+                match storage::valine::find_item_id_by_url(tx, feed_id, &url)? {
+                    Some(c) => Ok(storage::items::get_items(tx, "with_ids", &c.to_string(), 1)?),
+                    None => Ok(vec![]),
+                }
+            })?
             .iter()
             .map(to_counter)
             .collect();
@@ -89,7 +92,7 @@ impl valine::Valine {
     pub fn increment_counter(&self, id: &str) -> Result<Response<Full<Bytes>>, common::PipeError> {
         match id.parse() {
             Ok(v) => storage::transaction(&self.db, |tx| {
-                storage::valine::increment_item_counter(tx, v);
+                storage::valine::increment_item_counter(tx, v)?;
                 common::json_response(&format!(
                     "{{\"objectId\": \"{}\", \"updatedAt\": \"{}\"}}",
                     id,
@@ -1,17 +1,20 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use base64::{Engine, prelude::BASE64_STANDARD};
 use http::{HeaderMap, HeaderValue};
 use openssl::hash::{MessageDigest, hash};
 use serde::{Deserialize, Serialize};
 
+use crate::push;
+
 mod comment;
 mod counter;
 
 const DEFAULT_DATETIME: &str = "2000-01-01T00:00:00.000Z";
 
 pub struct Valine {
-    bark: String,
+    push: Arc<push::Push>,
     path: String,
     pub db: String,
     pub auth: String,
@@ -47,11 +50,11 @@ pub fn get_feed_id(auth: &str, headers: &HeaderMap<HeaderValue>) -> Option<u64>
 }
 
 impl Valine {
-    pub fn new(db: &str, auth: &str, bark: &str, path: &str) -> Self {
+    pub fn new(db: &str, auth: &str, push: Arc<push::Push>, path: &str) -> Self {
         Self {
             db: db.to_owned(),
             auth: auth.to_owned(),
-            bark: bark.to_owned(),
+            push,
             path: format!("/{}/", path),
         }
     }